@@ -2,7 +2,7 @@ import json
 
 import pytest
 
-from  dicto_r import dictor
+from  dicto_r import dictor, PathNotFound, PathSyntaxError
 
 with open("basic.json") as data:
         BASIC = json.load(data)
@@ -71,6 +71,18 @@ def test_escape_pathsep():
     result = dictor(BASIC, "dirty\.harry.genre")
     assert result == "romance"
 
+def test_bracket_keys_need_escaping():
+    """test keys holding brackets, which paths read as indexes"""
+    data = {"tags[0]": 1, "a/b": {"x": 2}}
+    assert dictor(data, "tags[0]") is None
+    assert dictor(data, ["tags[0]"]) == 1
+    assert dictor(data, '["tags[0]"]') == 1
+    assert dictor(data, r"tags\[0\]") == 1
+    with pytest.raises(PathNotFound) as e:
+        dictor(data, ["a/b", "c"], checknone=True)
+    assert e.value.resolved_path == '["a/b"]'
+    assert dictor(data, e.value.resolved_path) == {"x": 2}
+
 def test_ignore_letter_casing():
     """test ignoring letter upper/lower case"""
     result = dictor(BASIC, "austin PoWeRs.year", ignorecase=True)
@@ -146,8 +158,8 @@ def test_empty_string_path():
 
 def test_malformed_search_path():
     """test_malformed_search_path"""
-    result = dictor(BASIC, "spaceballs.")
-    assert result is None
+    with pytest.raises(PathSyntaxError):
+        dictor(BASIC, "spaceballs.")
 
 def test_non_existent_path_search():
     """test_non_existent_path_search"""
@@ -282,3 +294,76 @@ def test_nested_json_value():
 
     result = dictor(NESTED, "k1.k2.k3.k4.k5.k6.k7.k8.k9.k10")
     assert isinstance(result, dict)
+
+
+def test_mappings_and_sequences():
+    from collections import ChainMap, UserDict
+    from types import MappingProxyType
+
+    data = MappingProxyType({"movies": (UserDict(name="alien"), ChainMap({"name": "heat"}))})
+    assert dictor(data, "movies.1.name") == "heat"
+    assert dictor(data, "movies.0.Name", ignorecase=True) == "alien"
+    assert dictor(data, search="name") == ["alien", "heat"]
+
+
+def test_getattr():
+    from types import SimpleNamespace
+
+    order = {"customer": SimpleNamespace(address={"city": "Lyon"}, _secret=1)}
+    assert dictor(order, "customer.address.city", getattr=True) == "Lyon"
+    assert dictor(order, "customer.address.city") is None
+    assert dictor(order, "customer._secret", getattr=True) is None
+    assert dictor({"a": "x"}, "a.upper", getattr=True) is None
+    assert dictor(order, "customer.address.keys", getattr=True) is None
+    assert dictor({"n": 1}, "n.real", getattr=True, default="?") == "?"
+
+
+def test_typed_keys():
+    import enum
+
+    class Color(enum.Enum):
+        RED = 1
+
+    data = {"a": {3: {("x", "y"): "tuple", Color.RED: "red"}}, "b": {"3": "str", "#3": "hash"}}
+    assert dictor(data, ["a", 3, ("x", "y")]) == "tuple"
+    assert dictor(data, ("a", 3, Color.RED)) == "red"
+    assert dictor(data, "a.#3") == {("x", "y"): "tuple", Color.RED: "red"}
+    assert dictor(data, "b.3") == "str"
+    assert dictor(data, "b.#3") is None
+    assert dictor(data, r"b.\#3") == "hash"
+
+
+def test_key_list_paths():
+    from dicto_r import dictor_json, explain
+
+    data = {"dirty.harry": {"genre": ["crime", "thriller"]}}
+    assert dictor(data, ["dirty.harry", "genre", "1"]) == "thriller"
+    assert dictor(data, ("dirty.harry", "genre", -1)) == "thriller"
+    assert dictor_json(json.dumps(data), ["dirty.harry", "genre", 0]) == "crime"
+    assert explain(data, ["dirty.harry", "year"])[-1]["found"] is False
+
+
+def test_dictor_wrapper():
+    from dicto_r import Dictor, PathNotFound
+
+    assert Dictor.__module__ == "dicto_r"
+    doc = Dictor(BASIC, default="n/a")
+    assert doc["terminator.1.terminator 2.genre.0"] == "nuclear war"
+    assert doc.get("terminator.9") == "n/a"
+    assert "terminator.1" in doc
+    assert "terminator.9" not in doc
+    with pytest.raises(PathNotFound):
+        doc["terminator.9"]
+
+    doc = Dictor({"a": {}})
+    doc.sub("a").set("b.c", 1)
+    assert doc.data == {"a": {"b": {"c": 1}}}
+
+    from dicto_r import PathSyntaxError
+
+    doc = Dictor({"a": [1]})
+    with pytest.raises(PathNotFound):
+        doc.set("a.5", 1)
+    with pytest.raises(PathSyntaxError):
+        doc.set([], 1)
+    assert doc.data == {"a": [1]}
@@ -0,0 +1,170 @@
+//! Command line front-end for dictor lookups over JSON documents.
+//!
+//! Reads a JSON document from a file (or stdin) and resolves `--path` and/or
+//! `--search` with the same semantics as the python `dictor` function:
+//!
+//! ```text
+//! $ dictor basic.json --path "terminator.1.terminator 2.genre.0" --raw
+//! nuclear war
+//! $ cat list.json | dictor --search name
+//! ```
+
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::{ArgGroup, Parser};
+use serde_json::Value;
+
+use dicto_r::json::{find_occurences, replaced_by_default, resolve};
+use dicto_r::{convert_json, Converted, Input};
+
+
+const NOT_FOUND: u8 = 1;
+const BAD_INPUT: u8 = 2;
+const NOT_CONVERTED: u8 = 3;
+
+
+#[derive(Debug, Parser)]
+#[command(name = "dictor", about = "Query JSON documents with dictor paths", version)]
+#[command(group(ArgGroup::new("query").required(true).multiple(true).args(["path", "search"])))]
+struct Args{
+    /// JSON file to read. Reads stdin when omitted or "-"
+    file: Option<PathBuf>,
+    /// Dictionary key search path (pathsep separated). Slices are not supported
+    #[arg(short, long)]
+    path: Option<String>,
+    /// Collect every value stored under this key (below --path when given)
+    #[arg(short, long)]
+    search: Option<String>,
+    /// Value returned if the key is not found. Parsed as JSON, taken as a plain string otherwise
+    #[arg(short, long)]
+    default: Option<String>,
+    /// Treat upper/lower-case keys the same
+    #[arg(short, long)]
+    ignorecase: bool,
+    /// Path separator for --path. Sniffed from the path when omitted
+    #[arg(long)]
+    pathsep: Option<String>,
+    /// Convert the resolved value (as rtype does), keeping it (or --default) if it cannot be converted
+    #[arg(long, value_parser = ["str", "int", "float", "bool", "list", "dict"])]
+    rtype: Option<String>,
+    /// How --rtype int rounds fractional values
+    #[arg(long, requires = "rtype", value_parser = ["truncate", "floor", "ceil", "half_even", "half_up"])]
+    rounding: Option<String>,
+    /// Exit with a non-zero status if the value cannot be converted to --rtype
+    #[arg(long, requires = "rtype")]
+    strict: bool,
+    /// Exit with a non-zero status if the value is not found
+    #[arg(short, long)]
+    checknone: bool,
+    /// Print strings without quotes and search results one per line
+    #[arg(short, long)]
+    raw: bool,
+}
+
+
+fn read_document(file: Option<&PathBuf>) -> Result<Value, String> {
+    let reader: Box<dyn Read> = match file {
+        Some(path) if path.as_os_str() != "-" => {
+            let file = File::open(path).map_err(|e| format!("{}: {e}", path.display()))?;
+            Box::new(BufReader::new(file))
+        },
+        _ => Box::new(io::stdin().lock())
+    };
+    serde_json::from_reader(reader).map_err(|e| format!("invalid JSON: {e}"))
+}
+
+
+fn render(value: &Value, raw: bool) -> String {
+    match value {
+        Value::String(content) if raw => content.to_owned(),
+        _ if raw => value.to_string(),
+        _ => serde_json::to_string_pretty(value).unwrap_or_default()
+    }
+}
+
+
+fn not_found(path: Option<&String>) -> ExitCode {
+    match path {
+        Some(path) => eprintln!("dictor: value not found for search path: {path}"),
+        None => eprintln!("dictor: value not found for search path")
+    }
+    ExitCode::from(NOT_FOUND)
+}
+
+
+fn run(args: Args) -> Result<ExitCode, String> {
+    let data = read_document(args.file.as_ref())?;
+    let default = args.default.map(|default| {
+        serde_json::from_str(&default).unwrap_or(Value::String(default))
+    });
+
+    let mut inner_object = &data;
+    if let Some(path) = &args.path{
+        let input = Input::from_path(path.to_owned(), args.pathsep.clone()).map_err(|e| e.to_string())?;
+        let keys = input.keys().ok_or("slices are not supported, see --help")?;
+        match resolve(&data, &keys, args.ignorecase) {
+            Some(item) => inner_object = item,
+            None => {
+                if let Some(default) = default{
+                    println!("{}", render(&default, args.raw));
+                    return Ok(ExitCode::SUCCESS)
+                }else if args.checknone{
+                    return Ok(not_found(Some(path)))
+                }
+                println!("{}", render(&Value::Null, args.raw));
+                return Ok(ExitCode::SUCCESS)
+            }
+        }
+    }
+
+    if let Some(search) = args.search.filter(|_| !inner_object.is_null()){
+        let mut accumulator = vec![];
+        find_occurences(&search, inner_object, &mut accumulator);
+        if let Some(default) = &default{
+            for item in accumulator.iter_mut().filter(|item| replaced_by_default(item)){
+                *item = default;
+            }
+        }
+        if accumulator.is_empty() && args.checknone{
+            return Ok(not_found(args.path.as_ref()))
+        }
+        if args.raw{
+            for item in accumulator{
+                println!("{}", render(item, true));
+            }
+        }else{
+            let items = Value::Array(accumulator.into_iter().cloned().collect());
+            println!("{}", render(&items, false));
+        }
+        return Ok(ExitCode::SUCCESS)
+    }
+
+    let Some(rtype) = &args.rtype else {
+        println!("{}", render(inner_object, args.raw));
+        return Ok(ExitCode::SUCCESS)
+    };
+    match convert_json(inner_object, rtype, args.rounding.as_deref())? {
+        Some(Converted::Value(converted)) => println!("{}", render(&converted, args.raw)),
+        Some(Converted::BigInt(digits)) => println!("{digits}"),
+        None if args.strict => {
+            eprintln!("dictor: cannot convert {inner_object} to {rtype}");
+            return Ok(ExitCode::from(NOT_CONVERTED))
+        },
+        None => println!("{}", render(default.as_ref().unwrap_or(inner_object), args.raw))
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+
+fn main() -> ExitCode {
+    match run(Args::parse()) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("dictor: {e}");
+            ExitCode::from(BAD_INPUT)
+        }
+    }
+}
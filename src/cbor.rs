@@ -0,0 +1,205 @@
+//! CBOR documents (behind the `cbor` feature).
+//!
+//! `dictor_cbor` walks the path over the decoded values and only builds
+//! python objects for the subtree found. Map keys keep their CBOR type, so
+//! integer keys are addressed as `"42"` (as in `dictor`) and byte string keys
+//! as `bytes` in lists of keys. Bignums (tags 2 and 3) load as python ints;
+//! any other tag loads as the value it wraps.
+
+use ciborium::value::Value;
+use pyo3::exceptions::PyValueError;
+use pyo3::types::{PyBytes, PyDict, PyList, PyTuple};
+use pyo3::{pyfunction, PyAny, PyObject, PyResult, Python, ToPyObject};
+
+use crate::tree::{self, Key, Node, Tree};
+use crate::{dictor_from, read_bytes, PathArg, RType};
+
+
+const POSITIVE_BIGNUM: u64 = 2;
+const NEGATIVE_BIGNUM: u64 = 3;
+
+
+impl Tree for Value {
+    fn node(&self) -> Node<'_, Self> {
+        match self {
+            Value::Map(map) => Node::Map(map),
+            Value::Array(list) => Node::Array(list),
+            Value::Tag(POSITIVE_BIGNUM | NEGATIVE_BIGNUM, _) => Node::Scalar,
+            Value::Tag(_, inner) => inner.node(),
+            _ => Node::Scalar
+        }
+    }
+
+    fn key(&self) -> Key<'_> {
+        match self {
+            Value::Text(s) => Key::Str(s),
+            Value::Integer(i) => Key::Int(i128::from(*i)),
+            Value::Tag(POSITIVE_BIGNUM | NEGATIVE_BIGNUM, _) => Key::Other,
+            Value::Tag(_, inner) => inner.key(),
+            _ => Key::Other
+        }
+    }
+}
+
+
+/// Converts `value`, building tuples instead of lists for keys so that
+/// array keys remain hashable.
+fn to_py(py: Python, value: &Value, as_key: bool) -> PyResult<PyObject> {
+    Ok(match value {
+        Value::Null => py.None(),
+        Value::Bool(b) => b.to_object(py),
+        Value::Integer(i) => i128::from(*i).to_object(py),
+        Value::Float(f) => f.to_object(py),
+        Value::Text(s) => s.to_object(py),
+        Value::Bytes(b) => PyBytes::new(py, b).to_object(py),
+        Value::Tag(tag @ (POSITIVE_BIGNUM | NEGATIVE_BIGNUM), inner) if inner.is_bytes() => {
+            let magnitude = py.get_type::<pyo3::types::PyLong>()
+                .call_method1("from_bytes", (to_py(py, inner, as_key)?, "big"))?;
+            if *tag == NEGATIVE_BIGNUM{
+                // encodes -1 - n
+                magnitude.call_method1("__rsub__", (-1,))?.to_object(py)
+            }else{
+                magnitude.to_object(py)
+            }
+        },
+        Value::Tag(_, inner) => to_py(py, inner, as_key)?,
+        Value::Array(list) => {
+            let items = list.iter().map(|item| to_py(py, item, as_key)).collect::<PyResult<Vec<_>>>()?;
+            if as_key{
+                PyTuple::new(py, items).to_object(py)
+            }else{
+                PyList::new(py, items).to_object(py)
+            }
+        },
+        Value::Map(map) => {
+            let dict = PyDict::new(py);
+            for (key, item) in map{
+                dict.set_item(to_py(py, key, true)?, to_py(py, item, false)?)?;
+            }
+            dict.to_object(py)
+        },
+        _ => return Err(PyValueError::new_err("invalid CBOR: unsupported value"))
+    })
+}
+
+
+fn decode(source: &PyAny) -> PyResult<Value> {
+    let bytes = read_bytes(source)?;
+    let mut reader = &bytes[..];
+    let value = ciborium::from_reader(&mut reader).map_err(|e| PyValueError::new_err(format!("invalid CBOR: {e}")))?;
+    if !reader.is_empty(){
+        return Err(PyValueError::new_err(format!("invalid CBOR: {} trailing bytes", reader.len())))
+    }
+    Ok(value)
+}
+
+
+/// Decodes a CBOR document into python objects.
+#[pyfunction]
+pub(crate) fn load_cbor(py: Python, source: &PyAny) -> PyResult<PyObject> {
+    to_py(py, &decode(source)?, false)
+}
+
+
+/// Same as `dictor` but takes CBOR encoded bytes.
+#[pyfunction]
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn dictor_cbor(py: Python,
+    source: &PyAny,
+    path: Option<PathArg>,
+    default: Option<PyObject>,
+    checknone: Option<bool>,
+    ignorecase: Option<bool>,
+    pathsep: Option<String>,
+    search: Option<String>,
+    rtype: Option<RType>,
+    rounding: Option<&str>,
+    strict: Option<bool>,
+    unit: Option<&str>,
+    tz: Option<&str>,
+    on_error: Option<&str>
+) -> PyResult<Option<PyObject>> {
+    let value = decode(source)?;
+    let (resolved, found) = match &path {
+        Some(path) => tree::resolve(&value, path.input(py, pathsep.clone())?.segments(), ignorecase.unwrap_or(false)),
+        None => (0, &value)
+    };
+    let data = to_py(py, found, false)?;
+    dictor_from(py, data.as_ref(py), resolved, path, default, checknone, ignorecase, pathsep, search, rtype, rounding, strict, unit, tz, on_error, None)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(value: &Value) -> Vec<u8> {
+        let mut bytes = vec![];
+        ciborium::into_writer(value, &mut bytes).unwrap();
+        bytes
+    }
+
+    fn document() -> Vec<u8> {
+        encode(&Value::Map(vec![
+            (Value::from("users"), Value::Array(vec![
+                Value::Map(vec![(Value::from("name"), Value::from("pepe"))]),
+            ])),
+            (Value::from(-7), Value::from("int key")),
+            (Value::Bytes(b"raw".to_vec()), Value::from("bytes key")),
+            (Value::from("big"), Value::Tag(NEGATIVE_BIGNUM, Box::new(Value::Bytes(vec![1, 0, 0, 0, 0, 0, 0, 0, 0])))),
+        ]))
+    }
+
+    #[test]
+    fn non_string_keys(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let source = PyBytes::new(py, &document());
+            let res = dictor_cbor(py, source, Some("-7".into()), None, None,
+                None, None, None, None, None, None, None, None, None).unwrap().unwrap();
+            assert_eq!(res.extract::<String>(py).unwrap(), "int key");
+            let keys = py.eval("[b'raw']", None, None).unwrap();
+            let res = dictor_cbor(py, source, Some(PathArg::Keys(keys)), None, None,
+                None, None, None, None, None, None, None, None, None).unwrap().unwrap();
+            assert_eq!(res.extract::<String>(py).unwrap(), "bytes key");
+            let res = dictor_cbor(py, source, Some("users.0.name".into()), None, None,
+                None, None, None, None, None, None, None, None, None).unwrap().unwrap();
+            assert_eq!(res.extract::<String>(py).unwrap(), "pepe");
+        });
+    }
+
+    #[test]
+    fn bignums(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let source = PyBytes::new(py, &document());
+            let res = dictor_cbor(py, source, Some("big".into()), None, None,
+                None, None, None, None, None, None, None, None, None).unwrap().unwrap();
+            assert_eq!(res.to_string(), "-18446744073709551617");
+        });
+    }
+
+    #[test]
+    fn trailing_bytes_are_rejected(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let mut bytes = document();
+            bytes.push(0xf6);
+            let e = load_cbor(py, PyBytes::new(py, &bytes)).unwrap_err();
+            assert!(e.to_string().contains("1 trailing bytes"));
+            let source = pyo3::types::PyByteArray::new(py, &document());
+            assert!(load_cbor(py, source).is_ok());
+        });
+    }
+
+    #[test]
+    fn paths_resolve_through_tags(){
+        let value = Value::Map(vec![
+            (Value::from("a"), Value::Tag(42, Box::new(Value::Array(vec![Value::from(1), Value::from(2)])))),
+        ]);
+        let input = crate::Input::from_path("a.-1".into(), None).unwrap();
+        let (resolved, found) = tree::resolve(&value, input.segments(), false);
+        assert_eq!(resolved, 2);
+        assert_eq!(found, &Value::from(2));
+    }
+}
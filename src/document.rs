@@ -0,0 +1,256 @@
+//! `Dictor`: a document wrapped along with the options it is looked up with.
+//!
+//! Lookups go through `dictor` (and its traversal), options given to a call
+//! overriding the ones the document was wrapped with. `sub` views share the
+//! wrapped document, so values `set` through them are set in it.
+
+// pyo3 0.19 expands `#[new]` into impls nested in its trampoline
+#![allow(non_local_definitions)]
+
+use pyo3::types::{PyDict, PyString};
+use pyo3::{pyclass, pymethods, PyAny, PyErr, PyObject, PyResult, Python, ToPyObject};
+
+use crate::path::Segment;
+use crate::traverse::{self, Outcome};
+use crate::errors::PathSyntaxError;
+use crate::{dictor, type_name, Input, Location, PathArg, PathNotFound, RType, TypeMismatch};
+
+
+#[pyclass(module = "dicto_r")]
+pub(crate) struct Dictor{
+    data: PyObject,
+    default: Option<PyObject>,
+    checknone: bool,
+    ignorecase: bool,
+    pathsep: Option<String>,
+    getattr: bool
+}
+
+
+/// The python key `segment` stands for, when it is set in a mapping (or in a
+/// sequence, where keys are indexes).
+fn key_object(py: Python, segment: &Segment, sequence: bool) -> PyResult<PyObject> {
+    Ok(match segment {
+        Segment::Key(key) => match key.parse::<i64>() {
+            Ok(index) if sequence => index.to_object(py),
+            _ => key.to_object(py)
+        },
+        Segment::Int(int) => int.to_object(py),
+        Segment::Raw(key) => key.object.clone_ref(py),
+        Segment::Slice(slice) => traverse::py_slice(py, slice)?.to_object(py)
+    })
+}
+
+
+impl Dictor {
+    /// Same document and options, wrapping `data` instead.
+    fn wrap(&self, py: Python, data: PyObject) -> Self {
+        Self {
+            data,
+            default: self.default.as_ref().map(|default| default.clone_ref(py)),
+            checknone: self.checknone,
+            ignorecase: self.ignorecase,
+            pathsep: self.pathsep.clone(),
+            getattr: self.getattr
+        }
+    }
+
+    /// Error raised setting `path` when its segment at `segment_index`
+    /// cannot be stepped through.
+    fn set_error(&self, py: Python, path: &PathArg, input: &Input, segment_index: usize, found: &PyAny, not_container: bool) -> PyErr {
+        let text = match path.text() {
+            Ok(text) => text,
+            Err(e) => return e
+        };
+        let location = Location {
+            path: Some(&text),
+            segments: input.segments(),
+            delimiter: input.delimiter(),
+            segment_index: Some(segment_index),
+            found_type: type_name(found),
+            ..Location::default()
+        };
+        let message = format!("cannot set {text} (cannot look {:?} up in {})",
+            input.segments()[segment_index].to_string(), location.found_type.as_deref().unwrap_or("?"));
+        match not_container {
+            true => location.error::<TypeMismatch>(py, message),
+            false => location.error::<PathNotFound>(py, message)
+        }
+    }
+}
+
+
+#[pymethods]
+impl Dictor {
+    #[new]
+    fn new(data: PyObject,
+        default: Option<PyObject>,
+        checknone: Option<bool>,
+        ignorecase: Option<bool>,
+        pathsep: Option<String>,
+        getattr: Option<bool>
+    ) -> Self {
+        Self {
+            data,
+            default,
+            checknone: checknone.unwrap_or(false),
+            ignorecase: ignorecase.unwrap_or(false),
+            pathsep,
+            getattr: getattr.unwrap_or(false)
+        }
+    }
+
+    /// The wrapped document.
+    #[getter]
+    fn data(&self, py: Python) -> PyObject {
+        self.data.clone_ref(py)
+    }
+
+    /// Returns the value at `path`, with the same fallbacks and casting rules
+    /// as `dictor`.
+    #[allow(clippy::too_many_arguments)]
+    fn get(&self, py: Python,
+        path: PathArg,
+        default: Option<PyObject>,
+        checknone: Option<bool>,
+        ignorecase: Option<bool>,
+        pathsep: Option<String>,
+        rtype: Option<RType>,
+        rounding: Option<&str>,
+        strict: Option<bool>,
+        unit: Option<&str>,
+        tz: Option<&str>,
+        on_error: Option<&str>
+    ) -> PyResult<Option<PyObject>> {
+        let default = default.or_else(|| self.default.as_ref().map(|default| default.clone_ref(py)));
+        dictor(py, self.data.as_ref(py), Some(path), default,
+            Some(checknone.unwrap_or(self.checknone)), Some(ignorecase.unwrap_or(self.ignorecase)),
+            pathsep.or_else(|| self.pathsep.clone()), None, rtype, rounding, strict, unit, tz, on_error, Some(self.getattr))
+    }
+
+    /// Collects every value stored under `key`, below `path` when given.
+    fn search(&self, py: Python,
+        key: String,
+        path: Option<PathArg>,
+        default: Option<PyObject>,
+        ignorecase: Option<bool>,
+        pathsep: Option<String>
+    ) -> PyResult<Option<PyObject>> {
+        let default = default.or_else(|| self.default.as_ref().map(|default| default.clone_ref(py)));
+        dictor(py, self.data.as_ref(py), path, default, Some(self.checknone), Some(ignorecase.unwrap_or(self.ignorecase)),
+            pathsep.or_else(|| self.pathsep.clone()), Some(key), None, None, None, None, None, None, Some(self.getattr))
+    }
+
+    /// Sets `value` at `path`, creating the dicts missing along the way.
+    fn set(&self, py: Python, path: PathArg, value: PyObject) -> PyResult<()> {
+        let input = path.input(py, self.pathsep.clone())?;
+        let segments = input.segments();
+        let Some((last, parents)) = segments.split_last() else {
+            let text = path.text()?;
+            let location = Location { path: Some(&text), ..Location::default() };
+            return Err(location.error::<PathSyntaxError>(py, "cannot set the empty path".into()))
+        };
+        let mut container = self.data.as_ref(py);
+        for (segment_index, segment) in parents.iter().enumerate(){
+            let step = traverse::step(py, container, segment, self.ignorecase, self.getattr);
+            container = match step.outcome {
+                Outcome::Found(item) => item,
+                Outcome::Missing if step.mapping => {
+                    let child = PyDict::new(py);
+                    container.set_item(key_object(py, segment, false)?, child)?;
+                    child
+                },
+                outcome => {
+                    let not_container = matches!(outcome, Outcome::NotContainer);
+                    return Err(self.set_error(py, &path, &input, segment_index, container, not_container))
+                }
+            };
+        }
+        let step = traverse::step(py, container, last, self.ignorecase, self.getattr);
+        match (step.outcome, step.key) {
+            (Outcome::NotContainer, _) => Err(self.set_error(py, &path, &input, parents.len(), container, true)),
+            (_, Some(key)) if step.attribute => container.setattr(key.as_ref(py).downcast::<PyString>()?, value),
+            // keys matched (ignoring case, as bytes...) are replaced
+            (Outcome::Found(_), Some(key)) => container.set_item(key, value),
+            _ if step.mapping => container.set_item(key_object(py, last, false)?, value),
+            // sequences are not extended
+            _ => Err(self.set_error(py, &path, &input, parents.len(), container, false))
+        }
+    }
+
+    /// A view of the value at `path`, looked up with the same options.
+    fn sub(&self, py: Python, path: PathArg) -> PyResult<Self> {
+        let data = self.__getitem__(py, path)?.unwrap_or_else(|| py.None());
+        Ok(self.wrap(py, data))
+    }
+
+    /// Returns the value at `path`, raising `PathNotFound` (or
+    /// `TypeMismatch`) when it is missing.
+    fn __getitem__(&self, py: Python, path: PathArg) -> PyResult<Option<PyObject>> {
+        dictor(py, self.data.as_ref(py), Some(path), None, Some(true), Some(self.ignorecase),
+            self.pathsep.clone(), None, None, None, None, None, None, None, Some(self.getattr))
+    }
+
+    fn __contains__(&self, py: Python, path: PathArg) -> PyResult<bool> {
+        let input = path.input(py, self.pathsep.clone())?;
+        let mut inner_object = self.data.as_ref(py);
+        for segment in input.segments(){
+            match traverse::step(py, inner_object, segment, self.ignorecase, self.getattr).outcome {
+                Outcome::Found(item) => inner_object = item,
+                _ => return Ok(false)
+            }
+        }
+        Ok(true)
+    }
+
+    fn __repr__(&self, py: Python) -> PyResult<String> {
+        Ok(format!("Dictor({})", self.data.as_ref(py).repr()?))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lookups_use_the_wrapped_options(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let data = py.eval("{'Movies': [{'Name': 'alien'}]}", None, None).unwrap();
+            let document = Dictor::new(data.into(), Some("?".to_object(py)), None, Some(true), Some("/".into()), None);
+            let get = |path: &str| document.get(py, path.into(), None, None, None, None, None, None, None, None, None, None)
+                .unwrap().unwrap().to_string();
+            assert_eq!(get("movies/0/name"), "alien");
+            assert_eq!(get("movies/0/year"), "?");
+            assert!(document.__contains__(py, "movies/0".into()).unwrap());
+            assert!(!document.__contains__(py, "movies/1".into()).unwrap());
+            let e = document.__getitem__(py, "movies/1".into()).unwrap_err();
+            assert!(e.is_instance_of::<PathNotFound>(py));
+            let movie = document.sub(py, "movies/0".into()).unwrap();
+            assert_eq!(movie.__getitem__(py, "NAME".into()).unwrap().unwrap().to_string(), "alien");
+        });
+    }
+
+    #[test]
+    fn set_creates_missing_dicts(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let data = py.eval("{'a': [{'B': 1}], 's': 'x'}", None, None).unwrap();
+            let document = Dictor::new(data.into(), None, None, Some(true), None, None);
+            let set = |path: &str, value: i32| document.set(py, path.into(), value.to_object(py));
+            set("a.0.b", 2).unwrap();
+            set("a.-1.c.d", 3).unwrap();
+            set("e.f", 4).unwrap();
+            assert_eq!(data.to_string(), "{'a': [{'B': 2, 'c': {'d': 3}}], 's': 'x', 'e': {'f': 4}}");
+            assert!(set("s.t", 5).unwrap_err().is_instance_of::<TypeMismatch>(py));
+            assert!(set("a.3.b", 5).unwrap_err().is_instance_of::<PathNotFound>(py));
+            let e = set("a.5", 5).unwrap_err();
+            assert!(e.is_instance_of::<PathNotFound>(py));
+            assert_eq!(e.value(py).getattr("resolved_path").unwrap().to_string(), "a");
+            let empty = PathArg::Keys(pyo3::types::PyList::empty(py));
+            let e = document.set(py, empty, 1.to_object(py)).unwrap_err();
+            assert!(e.is_instance_of::<PathSyntaxError>(py));
+        });
+    }
+}
@@ -0,0 +1,120 @@
+//! Exceptions raised by lookups.
+//!
+//! They all derive from `DictorError`, itself a `ValueError`, and carry
+//! where the lookup failed:
+//!
+//! - `path`: the path looked up;
+//! - `segment_index`: index of the segment that could not be resolved;
+//! - `resolved_path`: the part of the path that did resolve (all of it for
+//!   conversion errors);
+//! - `found_type`: type name of the value found there;
+//! - `suggestions`: keys found there that the missing one was likely meant
+//!   to be (empty unless it was looked up in a dict).
+//!
+//! Details that are unknown (e.g. for raw JSON lookups) are `None`.
+//! `PathSyntaxError`s also carry the `offset` of the problem in the path, and
+//! `ExtractionError`s the `errors` found for every field.
+
+// pyo3 0.19 checks cfgs newer compilers don't know about in `create_exception!`
+#![allow(unexpected_cfgs)]
+
+use pyo3::{create_exception, PyErr, PyTypeInfo, Python, ToPyObject};
+use pyo3::exceptions::PyValueError;
+
+use crate::path::{render, ParseError, Segment};
+use crate::DOT;
+
+
+create_exception!(dicto_r, DictorError, PyValueError, "Base of the errors raised by lookups.");
+create_exception!(dicto_r, PathNotFound, DictorError, "A path segment was not found.");
+create_exception!(dicto_r, TypeMismatch, DictorError, "A path segment was looked up in a value that is not a container.");
+create_exception!(dicto_r, ConversionError, DictorError, "The value found could not be converted to `rtype`.");
+create_exception!(dicto_r, PathSyntaxError, DictorError, "The path cannot be parsed.");
+create_exception!(dicto_r, ExtractionError, DictorError, "Fields of a model are missing or cannot be converted.");
+
+
+/// Where a lookup failed.
+#[derive(Default)]
+pub(crate) struct Location<'a>{
+    pub(crate) path: Option<&'a str>,
+    /// Segments the path was split into
+    pub(crate) segments: &'a [Segment],
+    pub(crate) delimiter: Option<&'a str>,
+    pub(crate) segment_index: Option<usize>,
+    pub(crate) found_type: Option<String>,
+    pub(crate) suggestions: Vec<String>
+}
+
+impl Location<'_> {
+    /// Segments before the failing one, written back as a path.
+    fn resolved_path(&self) -> Option<String> {
+        let resolved = match self.segment_index {
+            Some(segment_index) => &self.segments[..segment_index.min(self.segments.len())],
+            None if !self.segments.is_empty() => self.segments,
+            None => return None
+        };
+        Some(render(resolved, self.delimiter.unwrap_or(DOT)))
+    }
+
+    /// Builds a `T` exception carrying this location.
+    pub(crate) fn error<T: PyTypeInfo>(&self, py: Python, message: String) -> PyErr {
+        self.attach(py, PyErr::new::<T, _>(message))
+    }
+
+    /// Sets this location as the attributes of `error`.
+    pub(crate) fn attach(&self, py: Python, error: PyErr) -> PyErr {
+        let value = error.value(py);
+        let attributes = [
+            ("path", self.path.to_object(py)),
+            ("segment_index", self.segment_index.to_object(py)),
+            ("resolved_path", self.resolved_path().to_object(py)),
+            ("found_type", self.found_type.to_object(py)),
+            ("suggestions", self.suggestions.to_object(py))
+        ];
+        for (name, attribute) in attributes{
+            if let Err(e) = value.setattr(name, attribute){
+                return e
+            }
+        }
+        error
+    }
+}
+
+
+/// `PathSyntaxError` raised for a path that cannot be parsed.
+pub(crate) fn syntax_error(py: Python, e: &ParseError) -> PyErr {
+    let location = Location { path: Some(&e.path), ..Location::default() };
+    let error = location.error::<PathSyntaxError>(py, e.to_string());
+    if let Err(e) = error.value(py).setattr("offset", e.offset){
+        return e
+    }
+    error
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors_carry_their_location(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let segments = ["a.b", "c", "d"].map(|key| Segment::Key(key.to_owned()));
+            let location = Location {
+                path: Some(r"a\.b.c.d"),
+                segments: &segments,
+                segment_index: Some(2),
+                found_type: Some("int".into()),
+                ..Location::default()
+            };
+            let error = location.error::<TypeMismatch>(py, "mismatch".into());
+            assert!(error.is_instance_of::<DictorError>(py));
+            assert!(error.is_instance_of::<PyValueError>(py));
+            let value = error.value(py);
+            assert_eq!(value.getattr("resolved_path").unwrap().to_string(), r"a\.b.c");
+            assert_eq!(value.getattr("segment_index").unwrap().extract::<usize>().unwrap(), 2);
+            assert_eq!(value.getattr("found_type").unwrap().to_string(), "int");
+        });
+    }
+}
@@ -0,0 +1,368 @@
+//! Builds dataclasses, `TypedDict`s, `NamedTuple`s and pydantic models out
+//! of the values found in a document.
+//!
+//! Every field is looked up at its own path: the one given in `mapping`, the
+//! `"dictor"` entry of its metadata (`field(metadata=...)` for dataclasses,
+//! `Field(json_schema_extra=...)` for pydantic) or, otherwise, its name. A
+//! path may also be given as a list (or tuple) of keys, or as a dict with a
+//! `"path"` and any of `"default"`, `"rtype"`, `"rounding"`, `"strict"`,
+//! `"unit"`, `"tz"` and `"on_error"` (any other key is rejected). Pydantic
+//! fields having an alias are looked up by their alias.
+
+use pyo3::exceptions::PyTypeError;
+use pyo3::types::{PyDict, PyList, PyString, PyTuple, PyType};
+use pyo3::{pyfunction, PyAny, PyObject, PyResult, Python, ToPyObject};
+
+use crate::errors::ExtractionError;
+use crate::{dictor, Conversion, Location, PathArg, PathNotFound, TypeMismatch};
+
+
+/// Metadata key holding the path (or lookup options) of a field.
+const METADATA_KEY: &str = "dictor";
+
+/// Keys of the dicts declaring a field along with its lookup options.
+const OPTIONS: [&str; 8] = ["path", "default", "rtype", "rounding", "strict", "unit", "tz", "on_error"];
+
+
+struct ModelField<'p>{
+    /// Name of the field, in `mapping` and errors
+    name: String,
+    /// Key the field is looked up at when no path is declared
+    key: String,
+    /// Keyword the model is built with
+    keyword: String,
+    /// Path (or lookup options) declared by the model itself
+    metadata: Option<&'p PyAny>,
+    /// Whether the model falls back to its own default when not given
+    has_default: bool
+}
+
+impl<'p> ModelField<'p> {
+    /// A field looked up and built by its name.
+    fn named(name: &PyAny, metadata: Option<&'p PyAny>, has_default: bool) -> PyResult<Self> {
+        let name: String = name.extract()?;
+        Ok(Self { key: name.clone(), keyword: name.clone(), name, metadata, has_default })
+    }
+}
+
+
+/// Fields of `model`, in declaration order.
+fn model_fields<'p>(py: Python<'p>, model: &'p PyAny) -> PyResult<Vec<ModelField<'p>>> {
+    let dataclasses = py.import("dataclasses")?;
+    let is_type = model.is_instance_of::<PyType>();
+    if is_type && dataclasses.getattr("is_dataclass")?.call1((model,))?.is_true()?{
+        let missing = dataclasses.getattr("MISSING")?;
+        let mut fields = vec![];
+        for field in dataclasses.getattr("fields")?.call1((model,))?.iter()?{
+            let field = field?;
+            if !field.getattr("init")?.is_true()?{
+                continue
+            }
+            fields.push(ModelField::named(
+                field.getattr("name")?,
+                field.getattr("metadata")?.call_method1("get", (METADATA_KEY,))?.extract()?,
+                !field.getattr("default")?.is(missing) || !field.getattr("default_factory")?.is(missing)
+            )?);
+        }
+        return Ok(fields)
+    }
+    if is_type && model.hasattr("model_fields")?{
+        // models validating by alias (the default) are built with aliases
+        let by_alias = model.getattr("model_config")?.call_method1("get", ("validate_by_alias", true))?.is_true()?;
+        let mut fields = vec![];
+        for (name, field) in model.getattr("model_fields")?.downcast::<PyDict>()?{
+            let name: String = name.extract()?;
+            let alias = match field.getattr("validation_alias")?.extract::<Option<String>>() {
+                Ok(Some(alias)) => Some(alias),
+                _ => field.getattr("alias")?.extract::<Option<String>>()?
+            };
+            let extra = field.getattr("json_schema_extra")?;
+            fields.push(ModelField {
+                key: alias.clone().unwrap_or_else(|| name.clone()),
+                keyword: alias.filter(|_| by_alias).unwrap_or_else(|| name.clone()),
+                name,
+                metadata: match extra.downcast::<PyDict>() {
+                    Ok(extra) => extra.get_item(METADATA_KEY),
+                    Err(_) => None
+                },
+                has_default: !field.call_method0("is_required")?.is_true()?
+            });
+        }
+        return Ok(fields)
+    }
+    let builtins = py.import("builtins")?;
+    let is_subclass = |base: &str| -> PyResult<bool> {
+        Ok(is_type && builtins.getattr("issubclass")?.call1((model, builtins.getattr(base)?))?.is_true()?)
+    };
+    if is_subclass("tuple")? && model.hasattr("_fields")?{
+        let defaults = model.getattr("_field_defaults")?;
+        return model.getattr("_fields")?.iter()?
+            .map(|name| {
+                let name = name?;
+                ModelField::named(name, None, defaults.contains(name)?)
+            })
+            .collect()
+    }
+    if is_subclass("dict")? && model.hasattr("__required_keys__")?{
+        let required = model.getattr("__required_keys__")?;
+        return model.getattr("__annotations__")?.iter()?
+            .map(|name| {
+                let name = name?;
+                ModelField::named(name, None, !required.contains(name)?)
+            })
+            .collect()
+    }
+    Err(PyTypeError::new_err(format!("cannot extract into {}: expected a dataclass, TypedDict, NamedTuple or pydantic model", model.repr()?)))
+}
+
+
+/// Looks a field up, returning `Ok(None)` when it is missing (and has no
+/// `"default"` option). Values that cannot be converted are errors unless
+/// `"strict"` or `"on_error"` say otherwise.
+fn lookup<'p>(py: Python<'p>,
+    data: &'p PyAny,
+    path: PathArg<'p>,
+    options: Option<&'p PyDict>,
+    ignorecase: Option<bool>,
+    pathsep: Option<String>
+) -> PyResult<Option<PyObject>> {
+    let option = |name: &str| options.and_then(|options| options.get_item(name));
+    let extract_option = |name: &str| -> PyResult<Option<&'p str>> {
+        option(name).map(|value| value.extract()).transpose()
+    };
+    let strict: Option<bool> = option("strict").map(|strict| strict.extract()).transpose()?;
+    let on_error = extract_option("on_error")?.or(match strict {
+        None => Some("raise"),
+        Some(_) => None
+    });
+    let conversion = Conversion::new(py, option("rtype").map(|rtype| rtype.extract()).transpose()?,
+        extract_option("rounding")?, strict, extract_option("unit")?, extract_option("tz")?, on_error)?;
+    let found = dictor(py, data, Some(path), None, Some(true), ignorecase, pathsep,
+        None, None, None, None, None, None, None, None);
+    let value = match found {
+        Ok(value) => value.unwrap_or_else(|| py.None()),
+        Err(e) if e.is_instance_of::<PathNotFound>(py) || e.is_instance_of::<TypeMismatch>(py) => {
+            return Ok(option("default").map(|default| default.to_object(py)))
+        },
+        Err(e) => return Err(e)
+    };
+    // values failing a validating conversion (decimal, uuid...) are reported
+    // rather than handled as missing
+    let converted = conversion.apply(py, value.as_ref(py), option("default"), true)?;
+    Ok(converted.map(|converted| converted.to_object(py)))
+}
+
+
+/// Builds `model` out of the values found in `data`.
+///
+/// `mapping` is a `{field: path}` dict overriding the paths the model
+/// declares. Every missing or invalid field is reported at once, in an
+/// `ExtractionError` whose `errors` attribute lists them as
+/// `(field, path, message)` tuples.
+#[pyfunction]
+pub(crate) fn extract(py: Python,
+    data: &PyAny,
+    model: &PyAny,
+    mapping: Option<&PyDict>,
+    ignorecase: Option<bool>,
+    pathsep: Option<String>
+) -> PyResult<PyObject> {
+    let fields = model_fields(py, model)?;
+    if let Some(mapping) = mapping{
+        for name in mapping.keys(){
+            if !fields.iter().any(|field| name.eq(&field.name).unwrap_or(false)){
+                return Err(PyTypeError::new_err(format!("{} has no field {}", model.repr()?, name.repr()?)))
+            }
+        }
+    }
+    let kwargs = PyDict::new(py);
+    let mut errors: Vec<(String, String, String)> = vec![];
+    for field in fields{
+        let declared = mapping.and_then(|mapping| mapping.get_item(&field.name)).or(field.metadata);
+        let (path, options): (PathArg, _) = match declared {
+            None => (field.key.clone().into(), None),
+            Some(path) if path.is_instance_of::<PyString>() || path.is_instance_of::<PyList>() || path.is_instance_of::<PyTuple>() => {
+                (path.extract()?, None)
+            },
+            Some(options) => {
+                let options = options.downcast::<PyDict>()
+                    .map_err(|_| PyTypeError::new_err(format!("invalid path for field {}: {}", field.name, options.repr().map(ToString::to_string).unwrap_or_default())))?;
+                if let Some(key) = options.keys().iter().find(|key| !OPTIONS.iter().any(|option| key.eq(option).unwrap_or(false))){
+                    return Err(PyTypeError::new_err(format!("invalid option for field {}: {} (expected {})", field.name, key.repr()?, OPTIONS.join(", "))))
+                }
+                let path = match options.get_item("path") {
+                    Some(path) => path.extract()?,
+                    None => field.key.clone().into()
+                };
+                (path, Some(options))
+            }
+        };
+        let found = lookup(py, data, path.clone(), options, ignorecase, pathsep.clone());
+        let path = path.text()?;
+        match found {
+            Ok(Some(value)) => kwargs.set_item(&field.keyword, value)?,
+            Ok(None) if field.has_default => {},
+            Ok(None) => errors.push((field.name, path, "missing".to_owned())),
+            Err(e) => errors.push((field.name, path, e.value(py).to_string()))
+        }
+    }
+    if !errors.is_empty(){
+        let model_name = model.getattr("__name__").map_or_else(|_| model.to_string(), |name| name.to_string());
+        let details: Vec<String> = errors.iter()
+            .map(|(field, path, message)| format!("  {field} ({path}): {message}"))
+            .collect();
+        let message = format!("cannot extract {model_name}, {} invalid field(s):\n{}", errors.len(), details.join("\n"));
+        let e = Location::default().error::<ExtractionError>(py, message);
+        let errors: Vec<&PyTuple> = errors.into_iter()
+            .map(|(field, path, message)| PyTuple::new(py, [field, path, message]))
+            .collect();
+        e.value(py).setattr("errors", PyList::new(py, errors))?;
+        return Err(e)
+    }
+    Ok(model.call((), Some(kwargs))?.to_object(py))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MODELS: &str = r#"
+import dataclasses, typing
+
+@dataclasses.dataclass
+class User:
+    name: str
+    age: int = dataclasses.field(metadata={"dictor": {"path": "info.age", "rtype": "int"}})
+    city: str = dataclasses.field(default="?", metadata={"dictor": "info.address.city"})
+
+class Point(typing.NamedTuple):
+    x: float
+    y: float = 0.0
+
+class Movie(typing.TypedDict, total=False):
+    title: str
+    year: int
+"#;
+
+    fn models(py: Python<'_>) -> &PyDict {
+        let globals = PyDict::new(py);
+        py.run(MODELS, Some(globals), None).unwrap();
+        globals
+    }
+
+    #[test]
+    fn dataclass_from_metadata_and_mapping(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let user = models(py).get_item("User").unwrap();
+            let data = py.eval("{'name': 'joe', 'info': {'age': '32'}, 'login': 'jdoe'}", None, None).unwrap();
+            let res = extract(py, data, user, None, None, None).unwrap();
+            assert_eq!(res.to_string(), "User(name='joe', age=32, city='?')");
+
+            let mapping = py.eval("{'name': 'login'}", None, None).unwrap().downcast().unwrap();
+            let res = extract(py, data, user, Some(mapping), None, None).unwrap();
+            assert_eq!(res.getattr(py, "name").unwrap().to_string(), "jdoe");
+        });
+    }
+
+    #[test]
+    fn tuples_and_typed_dicts(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let models = models(py);
+            let data = py.eval("{'x': 1.5, 'title': 'alien'}", None, None).unwrap();
+            let res = extract(py, data, models.get_item("Point").unwrap(), None, None, None).unwrap();
+            assert_eq!(res.to_string(), "Point(x=1.5, y=0.0)");
+            let res = extract(py, data, models.get_item("Movie").unwrap(), None, None, None).unwrap();
+            assert_eq!(res.to_string(), "{'title': 'alien'}");
+        });
+    }
+
+    #[test]
+    fn every_invalid_field_is_reported(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let user = models(py).get_item("User").unwrap();
+            let data = py.eval("{'info': {'age': 'old'}}", None, None).unwrap();
+            let e = extract(py, data, user, None, None, None).unwrap_err();
+            assert!(e.is_instance_of::<ExtractionError>(py));
+            assert!(e.is_instance_of::<crate::errors::DictorError>(py));
+            let errors: Vec<(String, String, String)> = e.value(py).getattr("errors").unwrap().extract().unwrap();
+            assert_eq!(errors[0], ("name".to_owned(), "name".to_owned(), "missing".to_owned()));
+            assert_eq!(errors[1].1, "info.age");
+            assert_eq!(errors.len(), 2);
+
+            let e = extract(py, data, PyString::new(py, "User"), None, None, None).unwrap_err();
+            assert!(e.is_instance_of::<PyTypeError>(py));
+
+            let mapping = py.eval("{'age': {'path': 'info.age', 'rtpye': 'int'}}", None, None).unwrap().downcast().unwrap();
+            let e = extract(py, data, user, Some(mapping), None, None).unwrap_err();
+            assert!(e.is_instance_of::<PyTypeError>(py));
+            assert!(e.value(py).to_string().starts_with("invalid option for field age: 'rtpye'"));
+        });
+    }
+
+    #[test]
+    fn conversion_failures_are_not_missing_values(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let user = models(py).get_item("User").unwrap();
+            let data = py.eval("{'name': 'joe', 'info': {'age': 'old'}}", None, None).unwrap();
+            let with_age = |age: &str| {
+                let mapping = py.eval(&format!("{{'age': {age}}}"), None, None).unwrap().downcast().unwrap();
+                extract(py, data, user, Some(mapping), None, None)
+            };
+            let e = with_age("{'path': 'info.age', 'rtype': 'int', 'default': 0}").unwrap_err();
+            let errors: Vec<(String, String, String)> = e.value(py).getattr("errors").unwrap().extract().unwrap();
+            assert_eq!(errors, [("age".to_owned(), "info.age".to_owned(), "cannot convert 'old' to int".to_owned())]);
+            // unless not strict, falling back to the default (or the value found)
+            let res = with_age("{'path': 'info.age', 'rtype': 'int', 'strict': False, 'default': 0}").unwrap();
+            assert_eq!(res.getattr(py, "age").unwrap().to_string(), "0");
+            let res = with_age("{'path': 'info.age', 'rtype': 'int', 'strict': False}").unwrap();
+            assert_eq!(res.getattr(py, "age").unwrap().to_string(), "old");
+            let e = with_age("{'path': 'info.age', 'rtype': 'uuid', 'strict': False}").unwrap_err();
+            let errors: Vec<(String, String, String)> = e.value(py).getattr("errors").unwrap().extract().unwrap();
+            assert_eq!(errors[0].2, "cannot convert 'old' to uuid");
+            let res = with_age("{'path': 'info.years', 'default': 7}").unwrap();
+            assert_eq!(res.getattr(py, "age").unwrap().to_string(), "7");
+        });
+    }
+
+    #[test]
+    fn pydantic_models(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            if py.import("pydantic").is_err(){
+                return
+            }
+            let globals = PyDict::new(py);
+            py.run(r#"
+import pydantic
+
+class Order(pydantic.BaseModel):
+    id: int = pydantic.Field(json_schema_extra={"dictor": {"path": "meta.id", "rtype": "int"}})
+    customer: str = pydantic.Field(alias="client")
+    total: float = 0.0
+
+class Invoice(Order):
+    model_config = pydantic.ConfigDict(validate_by_name=True, validate_by_alias=False)
+"#, Some(globals), None).unwrap();
+            let data = py.eval("{'meta': {'id': '7'}, 'client': 'joe'}", None, None).unwrap();
+            for model in ["Order", "Invoice"]{
+                let model = globals.get_item(model).unwrap();
+                let res = extract(py, data, model, None, None, None).unwrap();
+                assert_eq!(res.to_string(), "id=7 customer='joe' total=0.0");
+            }
+            let order = globals.get_item("Order").unwrap();
+            let mapping = py.eval("{'customer': 'meta.id'}", None, None).unwrap().downcast().unwrap();
+            let res = extract(py, data, order, Some(mapping), None, None).unwrap();
+            assert_eq!(res.getattr(py, "customer").unwrap().to_string(), "7");
+            // fields are reported by name, along with the alias looked up
+            let e = extract(py, py.eval("{}", None, None).unwrap(), order, None, None, None).unwrap_err();
+            let errors: Vec<(String, String, String)> = e.value(py).getattr("errors").unwrap().extract().unwrap();
+            assert_eq!(errors.iter().map(|error| (error.0.as_str(), error.1.as_str())).collect::<Vec<_>>(),
+                [("id", "meta.id"), ("customer", "client")]);
+        });
+    }
+}
@@ -0,0 +1,128 @@
+//! Identifier conversions: `rtype="uuid"`, `"ip"` and `"ip_network"`.
+//!
+//! Values are validated in Rust and only then built as `uuid.UUID` and
+//! `ipaddress` objects.
+
+use std::net::IpAddr;
+
+use pyo3::types::{IntoPyDict, PyBool, PyBytes, PyLong, PyString, PyTuple};
+use pyo3::{PyAny, Python};
+
+
+/// Hexadecimal digits of a UUID written as `12345678-1234-...`, with or
+/// without hyphens, braces or `urn:uuid:` prefix.
+fn uuid_hex(content: &str) -> Option<String> {
+    let content = content.trim();
+    let content = content.strip_prefix("urn:uuid:").unwrap_or(content);
+    let content = content.strip_prefix('{').and_then(|c| c.strip_suffix('}')).unwrap_or(content);
+    let hex = match content.len() {
+        32 => content.to_owned(),
+        36 => {
+            let groups: Vec<&str> = content.split('-').collect();
+            if groups.iter().map(|group| group.len()).ne([8, 4, 4, 4, 12]){
+                return None
+            }
+            groups.concat()
+        },
+        _ => return None
+    };
+    hex.chars().all(|c| c.is_ascii_hexdigit()).then_some(hex)
+}
+
+
+pub(crate) fn to_uuid<'p>(py: Python<'p>, inner_object: &'p PyAny) -> Option<&'p PyAny> {
+    let uuid = py.import("uuid").ok()?.getattr("UUID").ok()?;
+    if inner_object.is_instance(uuid).ok()?{
+        Some(inner_object)
+    }else if let Ok(content) = inner_object.downcast::<PyString>(){
+        let hex = uuid_hex(content.to_str().ok()?)?;
+        uuid.call1((hex,)).ok()
+    }else if let Ok(content) = inner_object.downcast::<PyBytes>(){
+        (content.as_bytes().len() == 16).then_some(())?;
+        uuid.call((), Some([("bytes", content)].into_py_dict(py))).ok()
+    }else{
+        None
+    }
+}
+
+
+/// Parses an address given as text or as its integer value.
+fn ip_address(inner_object: &PyAny) -> Option<IpAddr> {
+    if let Ok(content) = inner_object.downcast::<PyString>(){
+        content.to_str().ok()?.trim().parse().ok()
+    }else if inner_object.is_instance_of::<PyLong>() && !inner_object.is_instance_of::<PyBool>(){
+        let address: u128 = inner_object.extract().ok()?;
+        Some(match u32::try_from(address) {
+            Ok(address) => IpAddr::from(address.to_be_bytes()),
+            Err(_) => IpAddr::from(address.to_be_bytes())
+        })
+    }else{
+        None
+    }
+}
+
+
+pub(crate) fn to_ip<'p>(py: Python<'p>, inner_object: &'p PyAny) -> Option<&'p PyAny> {
+    let ipaddress = py.import("ipaddress").ok()?;
+    let address_types = PyTuple::new(py, [ipaddress.getattr("IPv4Address").ok()?, ipaddress.getattr("IPv6Address").ok()?]);
+    if inner_object.is_instance(address_types).ok()?{
+        return Some(inner_object)
+    }
+    let address = ip_address(inner_object)?;
+    ipaddress.getattr("ip_address").ok()?.call1((address.to_string(),)).ok()
+}
+
+
+/// Parses `address/prefix` networks (a bare address being a single host
+/// network), rejecting those with host bits set.
+fn ip_network(content: &str) -> Option<(IpAddr, u8)> {
+    let (address, prefix) = match content.trim().split_once('/') {
+        Some((address, prefix)) => (address.parse::<IpAddr>().ok()?, Some(prefix.parse::<u8>().ok()?)),
+        None => (content.trim().parse::<IpAddr>().ok()?, None)
+    };
+    let (bits, max_prefix) = match address {
+        IpAddr::V4(address) => (u128::from(u32::from(address)) << 96, 32),
+        IpAddr::V6(address) => (u128::from(address), 128)
+    };
+    let prefix = prefix.unwrap_or(max_prefix);
+    if prefix > max_prefix{
+        return None
+    }
+    let host_bits = bits.checked_shl(u32::from(prefix)).unwrap_or(0);
+    (host_bits == 0).then_some((address, prefix))
+}
+
+
+pub(crate) fn to_ip_network<'p>(py: Python<'p>, inner_object: &'p PyAny) -> Option<&'p PyAny> {
+    let ipaddress = py.import("ipaddress").ok()?;
+    let network_types = PyTuple::new(py, [ipaddress.getattr("IPv4Network").ok()?, ipaddress.getattr("IPv6Network").ok()?]);
+    if inner_object.is_instance(network_types).ok()?{
+        return Some(inner_object)
+    }
+    let (address, prefix) = ip_network(inner_object.downcast::<PyString>().ok()?.to_str().ok()?)?;
+    ipaddress.getattr("ip_network").ok()?.call1((format!("{address}/{prefix}"),)).ok()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uuids_are_validated(){
+        assert_eq!(uuid_hex("{12345678-1234-5678-1234-567812345678}").unwrap(), "12345678123456781234567812345678");
+        assert!(uuid_hex("urn:uuid:12345678123456781234567812345678").is_some());
+        assert!(uuid_hex("12345678-1234-5678-1234-56781234567g").is_none());
+        assert!(uuid_hex("1234567-81234-5678-1234-567812345678").is_none());
+    }
+
+    #[test]
+    fn networks_are_validated(){
+        assert_eq!(ip_network("10.0.0.0/8").unwrap().1, 8);
+        assert_eq!(ip_network("10.1.2.3").unwrap().1, 32);
+        assert!(ip_network("10.0.0.1/24").is_none());
+        assert!(ip_network("10.0.0.0/33").is_none());
+        assert_eq!(ip_network("2001:db8::/32").unwrap().1, 32);
+        assert!(ip_network("2001:db8::1/64").is_none());
+    }
+}
@@ -0,0 +1,191 @@
+//! Sketches a JSON Schema out of sample documents.
+//!
+//! Samples are walked down their mappings and sequences (as `dictor` walks
+//! them), merging what is found at each position: the types seen, the schema
+//! of items and, for mappings, the schema of every key along with how often
+//! it is present. JSON Schema properties are named by strings, so keys of
+//! other types (the integer key `1`, tuples...) are left out of the schema.
+
+use indexmap::IndexMap;
+use pyo3::exceptions::PyValueError;
+use pyo3::types::{PyDict, PyList, PyString, PyTuple};
+use pyo3::{pyfunction, PyAny, PyObject, PyResult, Python, ToPyObject};
+
+use crate::schema::JsonType;
+use crate::traverse::mapping_items;
+
+
+const DRAFT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+
+/// What was found at a position of the samples.
+#[derive(Default)]
+struct Shape{
+    /// How many values were found
+    count: usize,
+    /// Types found, in the order they first appeared
+    types: Vec<JsonType>,
+    /// Whether values JSON has no type for were found
+    untyped: bool,
+    /// Items of the lists found, once one is
+    items: Option<Box<Shape>>,
+    /// How many mappings were found
+    objects: usize,
+    /// String keys found, along with the shape of their values
+    properties: IndexMap<String, Shape>
+}
+
+impl Shape {
+    fn add(&mut self, value: &PyAny) -> PyResult<()> {
+        self.count += 1;
+        let Some(json_type) = JsonType::of(value) else {
+            self.untyped = true;
+            return Ok(())
+        };
+        if !self.types.contains(&json_type){
+            self.types.push(json_type);
+        }
+        match json_type {
+            JsonType::Array => {
+                let items = self.items.get_or_insert_with(Default::default);
+                for item in value.iter()?{
+                    items.add(item?)?;
+                }
+            },
+            JsonType::Object => {
+                self.objects += 1;
+                for (key, item) in mapping_items(value){
+                    if let Ok(key) = key.downcast::<PyString>(){
+                        self.properties.entry(key.to_str()?.to_owned()).or_default().add(item)?;
+                    }
+                }
+            },
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Writes the schema into `schema`. Keys present in at least
+    /// `required_ratio` of the dicts are required.
+    fn write(&self, py: Python, schema: &PyDict, required_ratio: f64) -> PyResult<()> {
+        let has_number = self.types.contains(&JsonType::Number);
+        // integers are numbers too
+        let types: Vec<&str> = self.types.iter()
+            .filter(|json_type| !(has_number && **json_type == JsonType::Integer))
+            .map(JsonType::name)
+            .collect();
+        match types.as_slice() {
+            _ if self.untyped => {},
+            [] => {},
+            [json_type] => schema.set_item("type", json_type)?,
+            types => schema.set_item("type", types)?
+        }
+        if let Some(items) = self.items.as_ref().filter(|items| items.count > 0){
+            let items_schema = PyDict::new(py);
+            items.write(py, items_schema, required_ratio)?;
+            schema.set_item("items", items_schema)?;
+        }
+        if self.objects > 0{
+            let properties = PyDict::new(py);
+            let mut required = vec![];
+            for (key, shape) in &self.properties{
+                let property = PyDict::new(py);
+                shape.write(py, property, required_ratio)?;
+                properties.set_item(key, property)?;
+                if shape.count as f64 >= required_ratio * self.objects as f64{
+                    required.push(key);
+                }
+            }
+            schema.set_item("properties", properties)?;
+            if !required.is_empty(){
+                schema.set_item("required", required)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+
+/// Infers a JSON Schema from `samples`, a list (or tuple) of documents or a
+/// single document.
+///
+/// Keys are required when present in at least `required_ratio` (1.0 by
+/// default, that is always) of the dicts found at their position.
+#[pyfunction]
+pub(crate) fn infer_schema(py: Python, samples: &PyAny, required_ratio: Option<f64>) -> PyResult<PyObject> {
+    let required_ratio = required_ratio.unwrap_or(1.0);
+    if !(0.0..=1.0).contains(&required_ratio){
+        return Err(PyValueError::new_err(format!("required_ratio must be between 0 and 1, not {required_ratio}")))
+    }
+    let mut shape = Shape::default();
+    if samples.is_instance_of::<PyList>() || samples.is_instance_of::<PyTuple>(){
+        for sample in samples.iter()?{
+            shape.add(sample?)?;
+        }
+    }else{
+        shape.add(samples)?;
+    }
+    let schema = PyDict::new(py);
+    schema.set_item("$schema", DRAFT)?;
+    shape.write(py, schema, required_ratio)?;
+    Ok(schema.to_object(py))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn infer(py: Python, samples: &str, required_ratio: Option<f64>) -> String {
+        let samples = py.eval(samples, None, None).unwrap();
+        let schema = infer_schema(py, samples, required_ratio).unwrap();
+        let schema = schema.as_ref(py).downcast::<PyDict>().unwrap();
+        schema.del_item("$schema").unwrap();
+        schema.to_string()
+    }
+
+    #[test]
+    fn types_are_merged(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            assert_eq!(infer(py, "[1, 2.5]", None), "{'type': 'number'}");
+            assert_eq!(infer(py, "[1, None, 'a']", None), "{'type': ['integer', 'null', 'string']}");
+            assert_eq!(infer(py, "[[1, 2], [], ['a']]", None), "{'type': 'array', 'items': {'type': ['integer', 'string']}}");
+            assert_eq!(infer(py, "[[]]", None), "{'type': 'array'}");
+            assert_eq!(infer(py, "[b'raw', 1]", None), "{}");
+            assert_eq!(infer(py, "[(1, 2)]", None), "{'type': 'array', 'items': {'type': 'integer'}}");
+        });
+    }
+
+    #[test]
+    fn only_string_keys_are_properties(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let samples = py.eval("[{1: 'a', '1': 2, (1, 2): 3}, __import__('types').MappingProxyType({'1': 4, 1: 'b'})]", None, None).unwrap();
+            let schema = infer_schema(py, samples, None).unwrap();
+            let schema = schema.as_ref(py);
+            assert_eq!(schema.get_item("properties").unwrap().to_string(), "{'1': {'type': 'integer'}}");
+            assert_eq!(schema.get_item("required").unwrap().to_string(), "['1']");
+            // the schema inferred validates its samples
+            for sample in samples.iter().unwrap(){
+                let errors = crate::schema::validate(py, sample.unwrap(), schema).unwrap();
+                assert_eq!(errors.as_ref(py).len().unwrap(), 0);
+            }
+        });
+    }
+
+    #[test]
+    fn required_keys_follow_presence(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let samples = "[{'id': 1, 'tags': [{'k': 'a'}]}, {'id': 2, 'name': 'b'}, {'id': 3, 'name': 'c'}]";
+            assert_eq!(infer(py, samples, None), "{'type': 'object', 'properties': {\
+                'id': {'type': 'integer'}, \
+                'tags': {'type': 'array', 'items': {'type': 'object', 'properties': {'k': {'type': 'string'}}, 'required': ['k']}}, \
+                'name': {'type': 'string'}}, \
+                'required': ['id']}");
+            assert!(infer(py, samples, Some(0.6)).ends_with("'required': ['id', 'name']}"));
+            assert!(infer_schema(py, PyList::empty(py), Some(2.0)).is_err());
+        });
+    }
+}
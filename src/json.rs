@@ -0,0 +1,450 @@
+//! Path resolution over `serde_json` values.
+//!
+//! Mirrors the traversal `dictor` performs on Python objects, so a path
+//! resolves to the same element whether the document is a parsed Python
+//! dict or raw JSON handled entirely on the Rust side.
+
+use indexmap::IndexMap;
+use pyo3::exceptions::PyValueError;
+use pyo3::types::{PyBytes, PyDict, PyList};
+use pyo3::{pyfunction, PyAny, PyErr, PyObject, PyResult, Python, ToPyObject};
+use serde_json::value::RawValue;
+use serde_json::Value;
+
+use crate::{lookup_error, Conversion, Failure, Input, Location, PathArg, PathNotFound, RType};
+
+
+/// Keys `path` is made of, for lookups over JSON values (which cannot be
+/// sliced, nor keyed by anything but strings).
+pub(crate) fn path_keys(py: Python, path: &PathArg, pathsep: Option<String>) -> PyResult<Vec<String>> {
+    input_keys(&path.input(py, pathsep)?)
+}
+
+
+/// Keys of a path already split, see [`path_keys`].
+pub(crate) fn input_keys(input: &Input) -> PyResult<Vec<String>> {
+    input.keys()
+        .ok_or_else(|| PyValueError::new_err("slices and raw keys are only supported on python objects"))
+}
+
+
+/// Position of the list element addressed by `arg`, if it is an integer
+/// within bounds. Negative integers count from the end, as in python.
+pub(crate) fn list_position(arg: &str, len: usize) -> Option<usize> {
+    let num_arg = arg.parse::<i32>().ok()? as i64;
+    let pos = if num_arg < 0 { len as i64 + num_arg } else { num_arg };
+    if pos < 0 || pos >= len as i64 {
+        return None
+    }
+    Some(pos as usize)
+}
+
+
+/// Walks `value` following `args`, returning the element found at the end of
+/// the path or `None` when any segment cannot be resolved.
+///
+/// As in `dictor`, a segment that parses as an integer is first tried as a
+/// list index (negative indexes count from the end) and then as a plain key.
+pub fn resolve<'a>(value: &'a Value, args: &[String], ignorecase: bool) -> Option<&'a Value> {
+    let mut inner_object = value;
+    for arg in args{
+        inner_object = match inner_object {
+            Value::Object(map) => {
+                if ignorecase{
+                    let lowered_arg = arg.to_lowercase();
+                    map.iter()
+                        .find(|(k, _)| k.to_lowercase() == lowered_arg)
+                        .map(|(_, v)| v)?
+                }else{
+                    map.get(arg)?
+                }
+            },
+            Value::Array(list) => &list[list_position(arg, list.len())?],
+            _ => return None
+        };
+    }
+    Some(inner_object)
+}
+
+
+/// Where a path stopped resolving in a JSON document.
+#[derive(Debug)]
+pub struct Miss<'a>{
+    /// Index of the segment that could not be looked up
+    pub segment_index: usize,
+    /// JSON text of the value it was looked up in
+    pub found: &'a str
+}
+
+impl Miss<'_> {
+    /// Python type name of the value the segment was looked up in.
+    pub fn found_type(&self) -> &'static str {
+        raw_type(self.found)
+    }
+
+    /// Error `dictor` raises for the same miss, `path` being the path
+    /// `input` was split from.
+    pub(crate) fn error(&self, py: Python, path: &str, input: &Input) -> PyErr {
+        let failure = match self.found.as_bytes().first() {
+            Some(b'{') => {
+                let keys = serde_json::from_str::<IndexMap<String, &RawValue>>(self.found)
+                    .map(|map| map.into_keys().collect())
+                    .unwrap_or_default();
+                Failure::Missing { attribute: false, candidates: keys }
+            },
+            Some(b'[') => Failure::Missing { attribute: false, candidates: vec![] },
+            _ => Failure::NotContainer
+        };
+        let location = Location {
+            path: Some(path),
+            segments: input.segments(),
+            delimiter: input.delimiter(),
+            segment_index: Some(self.segment_index),
+            found_type: Some(self.found_type().to_owned()),
+            ..Location::default()
+        };
+        let arg = input.segments().get(self.segment_index).map(ToString::to_string).unwrap_or_default();
+        lookup_error(py, location, &arg, failure)
+    }
+}
+
+
+/// Python type name of the value `text` holds, without parsing it.
+fn raw_type(text: &str) -> &'static str {
+    match text.as_bytes().first() {
+        Some(b'{') => "dict",
+        Some(b'[') => "list",
+        Some(b'"') => "str",
+        Some(b't' | b'f') => "bool",
+        Some(b'n') => "NoneType",
+        _ if text.contains(['.', 'e', 'E']) => "float",
+        _ => "int"
+    }
+}
+
+
+/// Same as [`resolve`] but over unparsed JSON text: only the containers along
+/// the path are split into their members, every other value is skipped over
+/// (and validated) without being built. A path that cannot be resolved gives
+/// the [`Miss`] where it stopped.
+pub fn resolve_raw<'a>(raw: &'a RawValue, args: &[String], ignorecase: bool) -> serde_json::Result<Result<&'a RawValue, Miss<'a>>> {
+    let mut inner_object = raw;
+    for (segment_index, arg) in args.iter().enumerate(){
+        let text = inner_object.get().trim_start();
+        let found = match text.as_bytes().first() {
+            Some(b'{') => {
+                let map: IndexMap<String, &RawValue> = serde_json::from_str(text)?;
+                if ignorecase{
+                    let lowered_arg = arg.to_lowercase();
+                    map.iter()
+                        .find(|(k, _)| k.to_lowercase() == lowered_arg)
+                        .map(|(_, v)| *v)
+                }else{
+                    map.get(arg).copied()
+                }
+            },
+            Some(b'[') => {
+                let list: Vec<&RawValue> = serde_json::from_str(text)?;
+                list_position(arg, list.len()).map(|pos| list[pos])
+            },
+            _ => None
+        };
+        match found {
+            Some(item) => inner_object = item,
+            None => return Ok(Err(Miss { segment_index, found: text }))
+        }
+    }
+    Ok(Ok(inner_object))
+}
+
+
+/// Collects every value stored under the `target` key, recursing through
+/// nested objects and arrays the same way `find_occurences` does for Python
+/// objects. Matches are not recursed into.
+///
+/// Callers handling a `default` should replace the matches for which
+/// [`replaced_by_default`] holds.
+pub fn find_occurences<'a>(target: &str, searchable: &'a Value, accumulator: &mut Vec<&'a Value>){
+    match searchable {
+        Value::Array(list) => {
+            for element in list {
+                find_occurences(target, element, accumulator);
+            }
+        },
+        Value::Object(map) => {
+            for (key, matching_item) in map {
+                if key == target{
+                    accumulator.push(matching_item);
+                }else if matching_item.is_object() || matching_item.is_array(){
+                    find_occurences(target, matching_item, accumulator);
+                }
+            }
+        },
+        _ => {}
+    }
+}
+
+
+/// Same as [`find_occurences`] but over unparsed JSON text: only the
+/// containers are split into their members, and the matches are left unparsed.
+pub fn find_occurences_raw<'a>(target: &str, searchable: &'a RawValue, accumulator: &mut Vec<&'a RawValue>) -> serde_json::Result<()> {
+    let text = searchable.get().trim_start();
+    match text.as_bytes().first() {
+        Some(b'[') => {
+            let list: Vec<&RawValue> = serde_json::from_str(text)?;
+            for element in list {
+                find_occurences_raw(target, element, accumulator)?;
+            }
+        },
+        Some(b'{') => {
+            let map: IndexMap<String, &RawValue> = serde_json::from_str(text)?;
+            for (key, matching_item) in map {
+                if key == target{
+                    accumulator.push(matching_item);
+                }else{
+                    find_occurences_raw(target, matching_item, accumulator)?;
+                }
+            }
+        },
+        _ => {}
+    }
+    Ok(())
+}
+
+
+/// Strings and bools found by a search are always kept while any other
+/// matching value is replaced by the default, when one is given.
+pub fn replaced_by_default(value: &Value) -> bool {
+    !matches!(value, Value::Bool(_) | Value::String(_))
+}
+
+
+/// Builds the python object equivalent to what `json.loads` returns for `value`.
+pub fn to_py(py: Python, value: &Value) -> PyObject {
+    match value {
+        Value::Null => py.None(),
+        Value::Bool(b) => b.to_object(py),
+        Value::Number(n) => {
+            if let Some(i) = n.as_i64(){
+                i.to_object(py)
+            }else if let Some(u) = n.as_u64(){
+                u.to_object(py)
+            }else{
+                n.as_f64().unwrap_or(f64::NAN).to_object(py)
+            }
+        },
+        Value::String(s) => s.to_object(py),
+        Value::Array(list) => {
+            PyList::new(py, list.iter().map(|item| to_py(py, item))).to_object(py)
+        },
+        Value::Object(map) => {
+            let dict = PyDict::new(py);
+            for (key, item) in map{
+                dict.set_item(key, to_py(py, item)).unwrap();
+            }
+            dict.to_object(py)
+        }
+    }
+}
+
+
+/// Same as `dictor` but takes the JSON document as `str` or `bytes`. The
+/// document is skimmed in Rust and only the returned values are built as
+/// python objects.
+#[pyfunction]
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn dictor_json(py: Python,
+    raw: &PyAny,
+    path: Option<PathArg>,
+    default: Option<PyObject>,
+    checknone: Option<bool>,
+    ignorecase: Option<bool>,
+    pathsep: Option<String>,
+    search: Option<String>,
+    rtype: Option<RType>,
+    rounding: Option<&str>,
+    strict: Option<bool>,
+    unit: Option<&str>,
+    tz: Option<&str>,
+    on_error: Option<&str>
+) -> PyResult<Option<PyObject>> {
+    let ignorecase = ignorecase.unwrap_or(false);
+    let checknone = checknone.unwrap_or(false);
+    let conversion = Conversion::new(py, rtype, rounding, strict, unit, tz, on_error)?;
+    let invalid_json = |e: serde_json::Error| PyValueError::new_err(format!("invalid JSON: {e}"));
+
+    if path.is_none() && search.is_none(){
+        return Ok(None)
+    }
+
+    let bytes = match raw.downcast::<PyBytes>() {
+        Ok(raw_bytes) => raw_bytes.as_bytes(),
+        Err(_) => raw.extract::<&str>()?.as_bytes()
+    };
+    let mut inner_object: &RawValue = serde_json::from_slice(bytes).map_err(invalid_json)?;
+
+    if let Some(path) = &path{
+        let input = path.input(py, pathsep)?;
+        match resolve_raw(inner_object, &input_keys(&input)?, ignorecase).map_err(invalid_json)? {
+            Ok(item) => inner_object = item,
+            Err(_) if default.is_some() => return Ok(default),
+            Err(miss) if checknone => return Err(miss.error(py, &path.text()?, &input)),
+            Err(_) => return Ok(None)
+        }
+    }
+
+    let path = path.map(|path| path.text()).transpose()?;
+    let value: Value = serde_json::from_str(inner_object.get()).map_err(invalid_json)?;
+    if let Some(search) = search.filter(|_| !value.is_null()){
+        let mut accumulator = vec![];
+        find_occurences(&search, &value, &mut accumulator);
+        if accumulator.is_empty() && checknone{
+            let location = Location { path: path.as_deref(), ..Location::default() };
+            return Err(location.error::<PathNotFound>(py, format!("value not found for search key: {search}")));
+        }
+        let items = accumulator.into_iter().map(|item| match &default {
+            Some(default) if replaced_by_default(item) => default.clone_ref(py),
+            _ => to_py(py, item)
+        });
+        return Ok(Some(PyList::new(py, items).to_object(py)))
+    }
+
+    let inner_object = to_py(py, &value);
+    let default = default.as_ref().map(|default| default.as_ref(py));
+    Ok(conversion.apply(py, inner_object.as_ref(py), default, checknone)?.map(Into::into))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::Input;
+
+    fn args(path: &str) -> Vec<String> {
+        Input::from_path(path.to_owned(), None).unwrap().keys().unwrap()
+    }
+
+    #[test]
+    fn resolve_nested_list_and_dict(){
+        let data = json!({"terminator": [{}, {"terminator 2": {"genre": ["nuclear war", "scifi"]}}]});
+        let res = resolve(&data, &args("terminator.1.terminator 2.genre.0"), false);
+        assert_eq!(res, Some(&json!("nuclear war")));
+
+        let res = resolve(&data, &args("terminator.-1.terminator 2.genre.-1"), false);
+        assert_eq!(res, Some(&json!("scifi")));
+    }
+
+    #[test]
+    fn resolve_numeric_key_and_missing_index(){
+        let data = json!({"1492": {"year": 1986}, "item": [1, 2, 3]});
+        assert_eq!(resolve(&data, &args("1492.year"), false), Some(&json!(1986)));
+        assert_eq!(resolve(&data, &args("item.4"), false), None);
+        assert_eq!(resolve(&data, &args("item.0.deeper"), false), None);
+    }
+
+    #[test]
+    fn resolve_ignorecase_and_escape(){
+        let data = json!({"AUSTIN Powers": {"year": 1996}, "dirty.harry": {"genre": "romance"}});
+        assert_eq!(resolve(&data, &args("austin PoWeRs.Year"), true), Some(&json!(1996)));
+        assert_eq!(resolve(&data, &args("austin PoWeRs.Year"), false), None);
+        assert_eq!(resolve(&data, &args(r"dirty\.harry.genre"), false), Some(&json!("romance")));
+    }
+
+    #[test]
+    fn find_occurences_with_default(){
+        let data = json!([
+            {"status": false}, {"status": ""}, {"status": null},
+            {"nested": {"status": "subkey1 status"}}
+        ]);
+        let default = json!("fallback");
+        let mut accumulator = vec![];
+        find_occurences("status", &data, &mut accumulator);
+        let found: Vec<&Value> = accumulator.into_iter()
+            .map(|item| if replaced_by_default(item) { &default } else { item })
+            .collect();
+        assert_eq!(found, vec![&json!(false), &json!(""), &json!("fallback"), &json!("subkey1 status")]);
+    }
+
+    #[test]
+    fn resolve_raw_skims_document(){
+        let raw: &RawValue = serde_json::from_str(r#"{"AUSTIN Powers": {"year": 1996}, "list": [1, {"a": "b"}, 3]}"#).unwrap();
+        let res = resolve_raw(raw, &args("list.-2.a"), false).unwrap();
+        assert_eq!(res.unwrap().get(), r#""b""#);
+        let res = resolve_raw(raw, &args("austin powers.year"), true).unwrap();
+        assert_eq!(res.unwrap().get(), "1996");
+        let miss = resolve_raw(raw, &args("list.3"), false).unwrap().unwrap_err();
+        assert_eq!((miss.segment_index, miss.found_type()), (1, "list"));
+        let miss = resolve_raw(raw, &args("list.2.a"), false).unwrap().unwrap_err();
+        assert_eq!((miss.segment_index, miss.found, miss.found_type()), (2, "3", "int"));
+    }
+
+    #[test]
+    fn dictor_json_path_and_search(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let raw = PyBytes::new(py, br#"{"year": "1987", "friends": [{"name": "a"}, {"name": null}]}"#);
+            let res = dictor_json(py, raw, Some("year".into()), None, None,
+                None, None, None, Some("int".into()), None, None, None, None, None).unwrap().unwrap();
+            assert!(res.as_ref(py).is_instance_of::<pyo3::types::PyLong>());
+            assert_eq!(res.extract::<i64>(py).unwrap(), 1987);
+
+            let default = "fallback".to_object(py);
+            let res = dictor_json(py, raw, Some("friends".into()), Some(default), None,
+                None, None, Some("name".into()), None, None, None, None, None, None).unwrap().unwrap();
+            let content: Vec<String> = res.extract(py).unwrap();
+            assert_eq!(content, vec!["a", "fallback"]);
+        });
+    }
+
+    #[test]
+    fn dictor_json_missing_and_invalid(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let raw = "[1, 2, 3]".to_object(py);
+            let res = dictor_json(py, raw.as_ref(py), Some("4".into()), None, None,
+                None, None, None, None, None, None, None, None, None).unwrap();
+            assert!(res.is_none());
+            let res = dictor_json(py, raw.as_ref(py), Some("4".into()), None, Some(true),
+                None, None, None, None, None, None, None, None, None);
+            assert!(res.unwrap_err().is_instance_of::<PathNotFound>(py));
+
+            let raw = r#"{"a": {"year": 1987}}"#.to_object(py);
+            let e = dictor_json(py, raw.as_ref(py), Some("a.year.month".into()), None, Some(true),
+                None, None, None, None, None, None, None, None, None).unwrap_err();
+            assert!(e.is_instance_of::<crate::TypeMismatch>(py));
+            let e = e.value(py);
+            assert_eq!(e.getattr("segment_index").unwrap().extract::<usize>().unwrap(), 2);
+            assert_eq!(e.getattr("resolved_path").unwrap().extract::<String>().unwrap(), "a.year");
+            assert_eq!(e.getattr("found_type").unwrap().extract::<String>().unwrap(), "int");
+            let e = dictor_json(py, raw.as_ref(py), Some("a.yeer".into()), None, Some(true),
+                None, None, None, None, None, None, None, None, None).unwrap_err();
+            assert!(e.is_instance_of::<PathNotFound>(py));
+            assert_eq!(e.value(py).getattr("suggestions").unwrap().extract::<Vec<String>>().unwrap(), ["year"]);
+
+            let raw = "[1, 2".to_object(py);
+            let res = dictor_json(py, raw.as_ref(py), Some("0".into()), None, None,
+                None, None, None, None, None, None, None, None, None);
+            assert!(res.is_err());
+        });
+    }
+
+    #[test]
+    fn dictor_json_key_list_paths(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let raw = r#"{"a.b": [{"c": 1}]}"#.to_object(py);
+            let lookup = |keys: &str| {
+                let keys = py.eval(keys, None, None).unwrap();
+                dictor_json(py, raw.as_ref(py), Some(PathArg::Keys(keys)), None, Some(true),
+                    None, None, None, None, None, None, None, None, None)
+            };
+            assert_eq!(lookup("['a.b', 0, 'c']").unwrap().unwrap().to_string(), "1");
+            assert_eq!(lookup("('a.b', '-1', 'c')").unwrap().unwrap().to_string(), "1");
+            let e = lookup("['a.b', 1]").unwrap_err();
+            assert_eq!(e.value(py).getattr("path").unwrap().to_string(), r"a\.b.#1");
+            assert!(lookup("['a.b', ('c',)]").unwrap_err().is_instance_of::<PyValueError>(py));
+        });
+    }
+}
@@ -0,0 +1,484 @@
+//! Lookups over large JSON files kept on disk.
+//!
+//! `JsonFile` memory-maps the file and indexes the byte span of every value
+//! down to a given depth. Lookups follow the index and only skim (see
+//! `resolve_raw`) the part of the document below it, so repeated lookups
+//! never parse the whole file nor build it as python objects.
+
+// pyo3 0.19 expands `#[new]` into impls nested in its trampoline
+#![allow(non_local_definitions)]
+
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use indexmap::IndexMap;
+use memmap2::Mmap;
+use pyo3::exceptions::PyValueError;
+use pyo3::types::PyList;
+use pyo3::{pyclass, pymethods, PyObject, PyResult, Python, ToPyObject};
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
+use serde_json::Value;
+
+use crate::json::{find_occurences_raw, input_keys, list_position, path_keys, replaced_by_default, resolve_raw, to_py, Miss};
+use crate::{Conversion, Location, PathArg, PathNotFound, RType};
+
+
+const DEFAULT_DEPTH: usize = 1;
+
+
+#[derive(Debug, Deserialize, Serialize)]
+enum Children{
+    Object(IndexMap<String, Node>),
+    Array(Vec<Node>)
+}
+
+
+/// Byte span of a value within the file. Containers above the indexed depth
+/// also hold the spans of their members.
+#[derive(Debug, Deserialize, Serialize)]
+struct Node{
+    start: usize,
+    end: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    children: Option<Children>
+}
+
+impl Node {
+    fn build(base: usize, raw: &RawValue, depth: usize) -> serde_json::Result<Self> {
+        let text = raw.get().trim_start();
+        let start = text.as_ptr() as usize - base;
+        let end = start + text.len();
+        let children = match text.as_bytes().first() {
+            _ if depth == 0 => None,
+            Some(b'{') => {
+                let map: IndexMap<String, &RawValue> = serde_json::from_str(text)?;
+                let mut members = IndexMap::with_capacity(map.len());
+                for (key, item) in map{
+                    members.insert(key, Node::build(base, item, depth - 1)?);
+                }
+                Some(Children::Object(members))
+            },
+            Some(b'[') => {
+                let list: Vec<&RawValue> = serde_json::from_str(text)?;
+                let members = list.into_iter()
+                    .map(|item| Node::build(base, item, depth - 1))
+                    .collect::<serde_json::Result<_>>()?;
+                Some(Children::Array(members))
+            },
+            _ => None
+        };
+        Ok(Self { start, end, children })
+    }
+}
+
+
+/// Structural index of a file, valid as long as the file length and
+/// modification time match.
+#[derive(Debug, Deserialize, Serialize)]
+struct Index{
+    len: u64,
+    modified: u128,
+    depth: usize,
+    root: Node
+}
+
+impl Index {
+    fn build(mmap: &Mmap, len: u64, modified: u128, depth: usize) -> serde_json::Result<Self> {
+        let raw: &RawValue = serde_json::from_slice(mmap)?;
+        let root = Node::build(mmap.as_ptr() as usize, raw, depth)?;
+        Ok(Self { len, modified, depth, root })
+    }
+
+    /// Loads a persisted index, returning `None` if it is missing, unreadable
+    /// or stale.
+    fn load(index_path: &Path, len: u64, modified: u128, depth: usize) -> Option<Self> {
+        let file = File::open(index_path).ok()?;
+        let index: Index = serde_json::from_reader(std::io::BufReader::new(file)).ok()?;
+        (index.len == len && index.modified == modified && index.depth == depth).then_some(index)
+    }
+
+    fn save(&self, index_path: &Path) -> PyResult<()> {
+        let file = File::create(index_path)?;
+        serde_json::to_writer(std::io::BufWriter::new(file), self).map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+}
+
+
+/// What a path leads to in the indexed file.
+enum Lookup{
+    Found(usize, usize),
+    /// Segment that could not be looked up, and span of the value it was
+    /// looked up in
+    Missing(usize, usize, usize),
+    /// A span does not fit the mapped file: the persisted index is stale.
+    Stale
+}
+
+
+/// A JSON file on disk served with `dictor` semantics.
+///
+/// `depth` sets how many levels of the document are indexed. When `index` is
+/// given the index is loaded from (or saved to) that path, and rebuilt
+/// whenever the JSON file changes.
+#[pyclass(module = "dicto_r")]
+pub(crate) struct JsonFile{
+    mmap: Mmap,
+    index: Index,
+    index_path: Option<PathBuf>,
+    #[pyo3(get)]
+    path: PathBuf
+}
+
+impl JsonFile {
+    /// Maps the file at `path`, reusing the index persisted at `index_path`
+    /// when `reuse` is set and it is up to date.
+    fn open(path: PathBuf, index_path: Option<PathBuf>, depth: usize, reuse: bool) -> PyResult<Self> {
+        let file = File::open(&path)?;
+        let metadata = file.metadata()?;
+        let modified = metadata.modified()?.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos());
+        // SAFETY: the map is only read. Changes made to the file once it is
+        // mapped are not detected (the index is only checked against the
+        // length and modification time found here), and truncating it
+        // meanwhile makes reads past its new end raise SIGBUS.
+        let mmap = unsafe { Mmap::map(&file)? };
+
+        let loaded = index_path.as_deref()
+            .filter(|_| reuse)
+            .and_then(|index_path| Index::load(index_path, metadata.len(), modified, depth));
+        let index = match loaded {
+            Some(index) => index,
+            None => {
+                let index = Index::build(&mmap, metadata.len(), modified, depth)
+                    .map_err(|e| PyValueError::new_err(format!("invalid JSON: {e}")))?;
+                if let Some(index_path) = index_path.as_deref(){
+                    index.save(index_path)?;
+                }
+                index
+            }
+        };
+        Ok(Self { mmap, index, index_path, path })
+    }
+
+    /// Text of a span, `None` when it does not fit the mapped file.
+    fn text(&self, start: usize, end: usize) -> Option<&str> {
+        std::str::from_utf8(self.mmap.get(start..end)?).ok()
+    }
+
+    /// Byte span of the value found at `args`, if any.
+    fn locate(&self, args: &[String], ignorecase: bool) -> PyResult<Lookup> {
+        let mut node = &self.index.root;
+        for (pos, arg) in args.iter().enumerate(){
+            let missing = |node: &Node| match self.text(node.start, node.end) {
+                Some(_) => Lookup::Missing(pos, node.start, node.end),
+                None => Lookup::Stale
+            };
+            node = match &node.children {
+                Some(Children::Object(map)) => {
+                    let found = if ignorecase{
+                        let lowered_arg = arg.to_lowercase();
+                        map.iter().find(|(k, _)| k.to_lowercase() == lowered_arg).map(|(_, v)| v)
+                    }else{
+                        map.get(arg)
+                    };
+                    match found {
+                        Some(item) => item,
+                        None => return Ok(missing(node))
+                    }
+                },
+                Some(Children::Array(list)) => match list_position(arg, list.len()) {
+                    Some(list_pos) => &list[list_pos],
+                    None => return Ok(missing(node))
+                },
+                None => {
+                    // below the indexed depth: skim the rest of the path
+                    let Some(text) = self.text(node.start, node.end) else {
+                        return Ok(Lookup::Stale)
+                    };
+                    let raw: &RawValue = serde_json::from_str(text).map_err(|e| PyValueError::new_err(e.to_string()))?;
+                    let item = resolve_raw(raw, &args[pos..], ignorecase).map_err(|e| PyValueError::new_err(e.to_string()))?;
+                    let base = self.mmap.as_ptr() as usize;
+                    return Ok(match item {
+                        Ok(item) => {
+                            let start = item.get().as_ptr() as usize - base;
+                            Lookup::Found(start, start + item.get().len())
+                        },
+                        Err(miss) => {
+                            let start = miss.found.as_ptr() as usize - base;
+                            Lookup::Missing(pos + miss.segment_index, start, start + miss.found.len())
+                        }
+                    })
+                }
+            };
+        }
+        match self.text(node.start, node.end) {
+            Some(_) => Ok(Lookup::Found(node.start, node.end)),
+            None => Ok(Lookup::Stale)
+        }
+    }
+
+    /// Same as `locate`, rebuilding a stale index from the file as it is now.
+    fn span(&mut self, args: &[String], ignorecase: bool) -> PyResult<Result<(usize, usize), Miss<'_>>> {
+        let mut lookup = self.locate(args, ignorecase)?;
+        if let Lookup::Stale = lookup{
+            *self = Self::open(self.path.clone(), self.index_path.clone(), self.index.depth, false)?;
+            lookup = self.locate(args, ignorecase)?;
+        }
+        match lookup {
+            Lookup::Found(start, end) => Ok(Ok((start, end))),
+            Lookup::Missing(segment_index, start, end) => {
+                let found = self.text(start, end).unwrap_or_default();
+                Ok(Err(Miss { segment_index, found }))
+            },
+            Lookup::Stale => Err(PyValueError::new_err(format!("index does not match {}", self.path.display())))
+        }
+    }
+
+    /// Spans of the values stored under `target` below `node`, in the order
+    /// `find_occurences` finds them: the index is followed as deep as it goes
+    /// and the rest of the document skimmed.
+    fn occurences(&self, target: &str, node: &Node, accumulator: &mut Vec<(usize, usize)>) -> PyResult<()> {
+        match &node.children {
+            Some(Children::Object(map)) => {
+                for (key, item) in map{
+                    if key == target{
+                        accumulator.push((item.start, item.end));
+                    }else{
+                        self.occurences(target, item, accumulator)?;
+                    }
+                }
+            },
+            Some(Children::Array(list)) => {
+                for item in list{
+                    self.occurences(target, item, accumulator)?;
+                }
+            },
+            None => {
+                let text = self.text(node.start, node.end).ok_or_else(|| PyValueError::new_err("span out of the file"))?;
+                let raw: &RawValue = serde_json::from_str(text).map_err(|e| PyValueError::new_err(e.to_string()))?;
+                let mut found = vec![];
+                find_occurences_raw(target, raw, &mut found).map_err(|e| PyValueError::new_err(e.to_string()))?;
+                let base = self.mmap.as_ptr() as usize;
+                accumulator.extend(found.into_iter().map(|item| {
+                    let start = item.get().as_ptr() as usize - base;
+                    (start, start + item.get().len())
+                }));
+            }
+        }
+        Ok(())
+    }
+
+    fn value(&self, start: usize, end: usize) -> PyResult<Value> {
+        let text = self.text(start, end).ok_or_else(|| PyValueError::new_err("span out of the file"))?;
+        serde_json::from_str(text).map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+}
+
+#[pymethods]
+impl JsonFile {
+    #[new]
+    fn new(path: PathBuf, index: Option<PathBuf>, depth: Option<usize>) -> PyResult<Self> {
+        Self::open(path, index, depth.unwrap_or(DEFAULT_DEPTH), true)
+    }
+
+    /// Returns the value at `path`, with the same fallbacks and casting rules
+    /// as `dictor`.
+    #[allow(clippy::too_many_arguments)]
+    fn get(&mut self, py: Python,
+        path: PathArg,
+        default: Option<PyObject>,
+        checknone: Option<bool>,
+        ignorecase: Option<bool>,
+        pathsep: Option<String>,
+        rtype: Option<RType>,
+        rounding: Option<&str>,
+        strict: Option<bool>,
+        unit: Option<&str>,
+        tz: Option<&str>,
+        on_error: Option<&str>
+    ) -> PyResult<Option<PyObject>> {
+        let conversion = Conversion::new(py, rtype, rounding, strict, unit, tz, on_error)?;
+        let input = path.input(py, pathsep)?;
+        match self.span(&input_keys(&input)?, ignorecase.unwrap_or(false))? {
+            Ok((start, end)) => {
+                let inner_object = to_py(py, &self.value(start, end)?);
+                let default = default.as_ref().map(|default| default.as_ref(py));
+                Ok(conversion.apply(py, inner_object.as_ref(py), default, checknone.unwrap_or(false))?.map(Into::into))
+            },
+            Err(_) if default.is_some() => Ok(default),
+            Err(miss) if checknone.unwrap_or(false) => Err(miss.error(py, &path.text()?, &input)),
+            Err(_) => Ok(None)
+        }
+    }
+
+    /// Collects every value stored under `key`, below `path` when given.
+    #[allow(clippy::too_many_arguments)]
+    fn search(&mut self, py: Python,
+        key: String,
+        path: Option<PathArg>,
+        default: Option<PyObject>,
+        checknone: Option<bool>,
+        ignorecase: Option<bool>,
+        pathsep: Option<String>
+    ) -> PyResult<Option<PyObject>> {
+        let checknone = checknone.unwrap_or(false);
+        let input = path.as_ref().map(|path| path.input(py, pathsep)).transpose()?;
+        let args = match &input {
+            Some(input) => input_keys(input)?,
+            None => vec![]
+        };
+        let path = path.map(|path| path.text()).transpose()?;
+        let (start, end) = match self.span(&args, ignorecase.unwrap_or(false))? {
+            Ok(span) => span,
+            Err(miss) if default.is_none() && checknone => {
+                let (path, input) = (path.unwrap_or_default(), input.unwrap_or_default());
+                return Err(miss.error(py, &path, &input))
+            },
+            Err(_) => return Ok(default)
+        };
+        // searches of the whole document follow the index from its root
+        let found = Node { start, end, children: None };
+        let node = if args.is_empty() { &self.index.root } else { &found };
+        let mut accumulator = vec![];
+        self.occurences(&key, node, &mut accumulator)?;
+        if accumulator.is_empty() && checknone{
+            let location = Location { path: path.as_deref(), ..Location::default() };
+            return Err(location.error::<PathNotFound>(py, format!("value not found for search key: {key}")));
+        }
+        let items = accumulator.into_iter()
+            .map(|(start, end)| {
+                let item = self.value(start, end)?;
+                Ok(match &default {
+                    Some(default) if replaced_by_default(&item) => default.clone_ref(py),
+                    _ => to_py(py, &item)
+                })
+            })
+            .collect::<PyResult<Vec<_>>>()?;
+        Ok(Some(PyList::new(py, items).to_object(py)))
+    }
+
+    fn __contains__(&mut self, py: Python, path: PathArg) -> PyResult<bool> {
+        Ok(self.span(&path_keys(py, &path, None)?, false)?.is_ok())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_json(name: &str, content: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("dicto_r_{}_{name}", std::process::id()));
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    const DOCUMENT: &str = r#" {"movies": [{"name": "spaceballs", "year": 1987, "cast": {"lead": "Bill Pullman"}},
+        {"name": "titanic", "year": 1997}], "dirty.harry": {"genre": "romance"}}"#;
+
+    #[test]
+    fn get_follows_index_and_skims_below_it(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let path = write_json("get.json", DOCUMENT);
+            let mut json_file = JsonFile::new(path.clone(), None, Some(1)).unwrap();
+            let res = json_file.get(py, "movies.0.cast.lead".into(), None, None, None, None, None, None, None, None, None, None).unwrap().unwrap();
+            assert_eq!(res.extract::<String>(py).unwrap(), "Bill Pullman");
+            let res = json_file.get(py, r"dirty\.harry.GENRE".into(), None, None, Some(true), None, None, None, None, None, None, None).unwrap().unwrap();
+            assert_eq!(res.extract::<String>(py).unwrap(), "romance");
+            let res = json_file.get(py, "movies.-1.year".into(), None, None, None, None, Some("str".into()), None, None, None, None, None).unwrap().unwrap();
+            assert_eq!(res.extract::<String>(py).unwrap(), "1997");
+            assert!(json_file.get(py, "movies.2".into(), None, Some(true), None, None, None, None, None, None, None, None).is_err());
+            // misses above and below the indexed depth report where they stopped
+            for (path, segment_index, resolved_path, found_type) in [("movies.0.year.month", 3, "movies.0.year", "int"), ("movies.name", 1, "movies", "list")]{
+                let e = json_file.get(py, path.into(), None, Some(true), None, None, None, None, None, None, None, None).unwrap_err();
+                let expected = if found_type == "int" { e.is_instance_of::<crate::TypeMismatch>(py) } else { e.is_instance_of::<PathNotFound>(py) };
+                assert!(expected, "{path}");
+                let e = e.value(py);
+                assert_eq!(e.getattr("segment_index").unwrap().extract::<usize>().unwrap(), segment_index);
+                assert_eq!(e.getattr("resolved_path").unwrap().extract::<String>().unwrap(), resolved_path);
+                assert_eq!(e.getattr("found_type").unwrap().extract::<String>().unwrap(), found_type);
+            }
+            let e = json_file.get(py, "movie".into(), None, Some(true), None, None, None, None, None, None, None, None).unwrap_err();
+            assert_eq!(e.value(py).getattr("suggestions").unwrap().extract::<Vec<String>>().unwrap(), ["movies"]);
+            std::fs::remove_file(path).unwrap();
+        });
+    }
+
+    #[test]
+    fn search_below_path(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let path = write_json("search.json", DOCUMENT);
+            let mut json_file = JsonFile::new(path.clone(), None, None).unwrap();
+            let res = json_file.search(py, "name".into(), Some("movies".into()), None, None, None, None).unwrap().unwrap();
+            assert_eq!(res.extract::<Vec<String>>(py).unwrap(), vec!["spaceballs", "titanic"]);
+            let e = json_file.search(py, "name".into(), Some("series".into()), None, Some(true), None, None).unwrap_err();
+            assert!(e.is_instance_of::<PathNotFound>(py));
+            let e = json_file.search(py, "rating".into(), None, None, Some(true), None, None).unwrap_err();
+            assert!(e.is_instance_of::<PathNotFound>(py));
+            let res = json_file.search(py, "rating".into(), None, None, None, None, None).unwrap().unwrap();
+            assert!(res.as_ref(py).downcast::<PyList>().unwrap().is_empty());
+            std::fs::remove_file(path).unwrap();
+        });
+    }
+
+    #[test]
+    fn search_follows_index_and_skims_below_it(){
+        let content = r#"{"name": "root", "movies": [{"name": "spaceballs", "cast": [{"name": "Bill"}, {"name": {"name": "x"}}]},
+            {"year": 1997}, {"sequel": {"name": null}}], "name ": 1}"#;
+        let path = write_json("search_depth.json", content);
+        let value: Value = serde_json::from_str(content).unwrap();
+        let mut expected = vec![];
+        crate::json::find_occurences("name", &value, &mut expected);
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let expected = PyList::new(py, expected.into_iter().map(|item| to_py(py, item)));
+            for depth in 0..5{
+                let mut json_file = JsonFile::new(path.clone(), None, Some(depth)).unwrap();
+                let res = json_file.search(py, "name".into(), None, None, None, None, None).unwrap().unwrap();
+                assert!(res.as_ref(py).eq(expected).unwrap(), "depth {depth}: {res}");
+                let res = json_file.search(py, "name".into(), Some("movies.0.cast".into()), None, None, None, None).unwrap().unwrap();
+                assert_eq!(res.to_string(), "['Bill', {'name': 'x'}]");
+            }
+        });
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn persisted_index_is_reused(){
+        let path = write_json("persist.json", DOCUMENT);
+        let index_path = path.with_extension("idx");
+        let json_file = JsonFile::new(path.clone(), Some(index_path.clone()), None).unwrap();
+        let (len, modified) = (json_file.index.len, json_file.index.modified);
+        // an index renaming "movies" is only followed when it is reused
+        let mut index = Index::load(&index_path, len, modified, DEFAULT_DEPTH).unwrap();
+        let Some(Children::Object(map)) = &mut index.root.children else { panic!("root is an object") };
+        let movies = map.shift_remove("movies").unwrap();
+        map.insert("films".into(), movies);
+        index.save(&index_path).unwrap();
+        let mut json_file = JsonFile::new(path.clone(), Some(index_path.clone()), None).unwrap();
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            assert!(json_file.__contains__(py, "films.1.name".into()).unwrap());
+            assert!(!json_file.__contains__(py, "movies.1.name".into()).unwrap());
+        });
+        // a different depth invalidates the persisted index
+        assert!(Index::load(&index_path, len, modified, 3).is_none());
+
+        // spans past the end of the file are detected and the index rebuilt
+        let mut index = Index::load(&index_path, len, modified, DEFAULT_DEPTH).unwrap();
+        index.root = Node { start: 0, end: DOCUMENT.len() + 10, children: None };
+        index.save(&index_path).unwrap();
+        let mut json_file = JsonFile::new(path.clone(), Some(index_path.clone()), None).unwrap();
+        Python::with_gil(|py| {
+            assert!(json_file.__contains__(py, "movies.1.name".into()).unwrap());
+        });
+        let index = Index::load(&index_path, len, modified, DEFAULT_DEPTH).unwrap();
+        assert_eq!(index.root.end, DOCUMENT.len());
+        assert!(index.root.children.is_some());
+        std::fs::remove_file(path).unwrap();
+        std::fs::remove_file(index_path).unwrap();
+    }
+}
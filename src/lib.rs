@@ -8,41 +8,115 @@
 //!Dictor is polite with Exception errors commonly encountered when parsing large Dictionaries/JSONs.
 //!Using Dictor eliminates the repeated use of try/except blocks in your code when dealing with lookups of large JSON structures, as well as providing flexibility for inserting fallback values on missing keys/values.
 
-use std::fmt::Display;
-
-use pyo3::exceptions::PyTypeError;
-use pyo3::types::{PyString, PyList, PyBool, PyFloat, PyInt};
-use pyo3::{ToPyObject, PyAny, PyErr};
-use pyo3::{types::{PyDict, PyModule}, PyResult, pymodule, Python, PyObject, exceptions::PyValueError,
+use pyo3::types::{PyString, PyList, PyBool, PyLong, PySlice, PyTuple};
+use pyo3::{FromPyObject, ToPyObject, PyAny, PyErr};
+use pyo3::{types::PyModule, PyResult, pymodule, Python, PyObject,
 wrap_pyfunction, pyfunction};
 
 
-const DOT: &str = ".";
-const SLASH: &str = "/";
-
-
-#[derive(Debug)]
+pub mod json;
+mod document;
+mod errors;
+mod extract;
+mod identifiers;
+mod infer;
+mod json_file;
+mod ndjson;
+pub mod path;
+mod rtype;
+mod schema;
+mod suggest;
+mod temporal;
+mod traverse;
+#[cfg(feature = "cbor")]
+mod cbor;
+#[cfg(feature = "msgpack")]
+mod msgpack;
+#[cfg(feature = "toml")]
+mod toml;
+#[cfg(any(feature = "msgpack", feature = "cbor"))]
+mod tree;
+#[cfg(feature = "xml")]
+mod xml;
+#[cfg(feature = "yaml")]
+mod yaml;
+
+pub use path::{ParseError, RawKey, Segment};
+pub use rtype::{convert_json, Converted};
+pub(crate) use errors::{syntax_error, ConversionError, Location, PathNotFound, TypeMismatch};
+pub(crate) use path::DOT;
+pub(crate) use rtype::{Conversion, RType};
+use traverse::Outcome;
+
+#[derive(Debug, Default)]
 pub struct Input{
-    args: Vec<String>,
+    segments: Vec<Segment>,
     delimiter: Option<String>
 }
 impl Input {
-    fn new(raw_input: String, delimiter: String) -> Self {
-        let args = raw_input.split(&delimiter).map(|s| s.to_owned()).collect();
-        Self { args: args, delimiter: Some(delimiter) }
+    /// Splits `path` the same way `dictor` does: on `pathsep` when given,
+    /// sniffing the delimiter otherwise (see [`path`] for the syntax).
+    pub fn from_path(path: String, pathsep: Option<String>) -> Result<Self, ParseError> {
+        match pathsep {
+            Some(delimiter) => Ok(Self { segments: path::parse(&path, Some(&delimiter))?, delimiter: Some(delimiter) }),
+            None => Input::try_from(path)
+        }
     }
-}
 
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
 
-#[derive(Debug)]
-pub enum ParseError{
-    InvalidDelimiter(String),
-}
+    /// Takes the segments of a path given as a list (or tuple) of keys (see
+    /// `key_segment`).
+    pub(crate) fn from_keys(keys: &PyAny) -> PyResult<Self> {
+        if !keys.is_instance_of::<PyList>() && !keys.is_instance_of::<PyTuple>(){
+            let message = format!("path must be a str, list or tuple, not {}", type_name(keys).unwrap_or_default());
+            return Err(pyo3::exceptions::PyTypeError::new_err(message))
+        }
+        let segments = keys.iter()?.map(|key| key_segment(key?)).collect::<PyResult<_>>()?;
+        Ok(Self { segments, delimiter: None })
+    }
 
-impl Display for ParseError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_fmt(format_args!("{}", self))
+    /// The keys the path is made of, or `None` when it has slices or raw
+    /// keys.
+    pub fn keys(&self) -> Option<Vec<String>> {
+        self.segments.iter()
+            .map(|segment| match segment {
+                Segment::Key(key) => Some(key.to_owned()),
+                Segment::Int(int) => Some(int.to_string()),
+                Segment::Slice(_) | Segment::Raw(_) => None
+            })
+            .collect()
     }
+
+    /// The path written back, escaping what needs to be.
+    pub fn render(&self) -> String {
+        path::render(&self.segments, self.delimiter().unwrap_or(DOT))
+    }
+
+    pub fn delimiter(&self) -> Option<&str> {
+        self.delimiter.as_deref()
+    }
+}
+
+
+/// The segment looking `key` up: strings are keys, integers integer keys
+/// and slices slices, any other object being looked up as is.
+pub(crate) fn key_segment(key: &PyAny) -> PyResult<Segment> {
+    Ok(if let Ok(key) = key.downcast::<PyString>(){
+        Segment::Key(key.to_str()?.to_owned())
+    }else if let Some(int) = key.downcast::<PyLong>().ok().filter(|_| !key.is_instance_of::<PyBool>()).and_then(|int| int.extract().ok()){
+        Segment::Int(int)
+    }else if let Ok(slice) = key.downcast::<PySlice>(){
+        let bound = |name: &str| slice.getattr(name)?.extract::<Option<isize>>();
+        match (bound("start"), bound("stop"), bound("step")) {
+            (Ok(start), Ok(stop), Ok(step)) => Segment::Slice(path::Slice { start, stop, step }),
+            _ => Segment::Raw(RawKey::new(key)?)
+        }
+    }else{
+        Segment::Raw(RawKey::new(key)?)
+    })
 }
 
 
@@ -50,68 +124,55 @@ impl TryFrom<String> for Input{
     type Error = ParseError;
     
     fn try_from(value: String) -> Result<Self, Self::Error> {
-        let escaped_path = r"\.".to_string();
-        if value.contains(&escaped_path){
-            let mut args: Vec<String> = vec![];
-            let vec_value: Vec<&str> = value.split(".").collect();
-            let max_pos = vec_value.len();
-            let mut pos = 0;
-            while pos < max_pos{
-                let elm = vec_value[pos];
-                if elm.ends_with(r"\"){
-                    let elm = elm.replace(r"\", "");
-                    if let Some(part) = vec_value.get(pos+1){
-                        let merged_arg = format!("{elm}.{part}");
-                        args.push(merged_arg);
-                        // skip next iteam as it's been merged
-                        pos += 1;
-                    }
-                    
-                }else{
-                    args.push(elm.to_string());
-                }
-                pos +=1;
-            };
-            return Ok(Self{args: args, delimiter: Some(DOT.to_owned())})
-        }
-        let mut delimiter: Option<String>;
-        if let Some(_) = value.find(&DOT){
-            delimiter = Some(DOT.to_owned());
-        }else if let Some(_) = value.find(&SLASH){
-            delimiter = Some(SLASH.to_owned());
-        }else{
-            delimiter = None
-        }
-        
-        let args: Vec<String> = match delimiter.clone(){
-            Some(del)=> value.split(&del).map(|s| s.to_owned()).collect(),
-            None => vec![value]
-        };
-        Ok(Self { args: args, delimiter: delimiter })
-        
+        let delimiter = path::sniff_delimiter(&value);
+        Ok(Self { segments: path::parse(&value, delimiter)?, delimiter: delimiter.map(str::to_owned) })
     }
 }
 
-enum ReturnType{
-    STRING,
-    INT,
-    NONE
+/// A path given as text, or as the list (or tuple) of its keys.
+#[derive(Clone, FromPyObject)]
+pub(crate) enum PathArg<'p>{
+    Text(String),
+    Keys(&'p PyAny)
 }
 
-impl From<String> for ReturnType{
+impl From<String> for PathArg<'_>{
     fn from(value: String) -> Self {
-        return match value.as_str(){
-            "str" => ReturnType::STRING,
-            "int" => ReturnType::INT,
-            _ => ReturnType::NONE
+        PathArg::Text(value)
+    }
+}
+
+impl From<&str> for PathArg<'_>{
+    fn from(value: &str) -> Self {
+        PathArg::Text(value.to_owned())
+    }
+}
+
+impl PathArg<'_> {
+    /// Splits the path into its segments (see [`Input::from_path`] and
+    /// [`Input::from_keys`]).
+    pub(crate) fn input(&self, py: Python, pathsep: Option<String>) -> PyResult<Input> {
+        match self {
+            PathArg::Text(path) => Input::from_path(path.to_owned(), pathsep).map_err(|e| syntax_error(py, &e)),
+            PathArg::Keys(keys) => Input::from_keys(keys)
+        }
+    }
+
+    /// The path as text, lists of keys being written back (to be reported).
+    pub(crate) fn text(&self) -> PyResult<String> {
+        match self {
+            PathArg::Text(path) => Ok(path.to_owned()),
+            PathArg::Keys(keys) => Ok(Input::from_keys(keys)?.render())
         }
     }
 }
+
 /* 
 Args:
 data (dict | list): Input dictionary to be searched in.
-path (str, optional): Dictionary key search path (pathsep separated).
-    Defaults to None.
+path (str | list | tuple, optional): Dictionary key search path (pathsep
+    separated), or the list of its keys, looked up as is (e.g. ["a", 3,
+    ("x", "y")]). Defaults to None.
 default (Any, optional): Default value to return if the key is not found.
     Defaults to None.
 checknone (bool, optional): If set, an exception is thrown if the value
@@ -119,121 +180,119 @@ checknone (bool, optional): If set, an exception is thrown if the value
 ignorecase (bool, optional): If set, upper/lower-case keys are treated
     the same. Defaults to False.
 pathsep (str, optional): Path separator for path parameter. Defaults to ".".
-rtype=None,
+rtype (str | type | callable, optional): Type the found value is converted
+    to (str, int, float, bool, list, dict, bytes, datetime, date, time,
+    timedelta, decimal, uuid, ip or ip_network), given by name or type, or a
+    callable converting it. Defaults to None.
+rounding (str, optional): How rtype="int" rounds fractional values
+    (truncate, floor, ceil, half_even or half_up). Defaults to "truncate".
+strict (bool, optional): If set, an exception is thrown if the value cannot
+    be converted to rtype. Otherwise default (or the value itself) is
+    returned. Defaults to False.
+unit (str, optional): Unit of epoch timestamps and numeric durations (s, ms,
+    us or ns). Defaults to "s".
+tz (str, optional): Timezone datetimes are converted to, naive ones being
+    assumed to be in it. Defaults to None (kept as found).
+on_error (str, optional): What values that cannot be converted return:
+    raise, default or raw. Defaults to None (see README).
+getattr (bool, optional): If set, keys are looked up as attributes of
+    values that are neither mappings nor sequences (dataclasses,
+    SimpleNamespaces, ORM objects...), private ones aside. Defaults to False.
 */
 #[pyfunction]
+#[allow(clippy::too_many_arguments)]
 fn dictor(_py: Python, 
     data: & PyAny,
-    path: Option<String>, 
+    path: Option<PathArg>, 
     default: Option<PyObject>,
     checknone: Option<bool>,
     ignorecase: Option<bool>,
     pathsep: Option<String>,
     search: Option<String>,
-    rtype: Option<String>
+    rtype: Option<RType>,
+    rounding: Option<&str>,
+    strict: Option<bool>,
+    unit: Option<&str>,
+    tz: Option<&str>,
+    on_error: Option<&str>,
+    getattr: Option<bool>
+) -> PyResult<Option<PyObject>> {
+    dictor_from(_py, data, 0, path, default, checknone, ignorecase, pathsep, search, rtype, rounding, strict, unit, tz, on_error, getattr)
+}
+
+
+/// Same as `dictor`, `data` being the value the first `resolved` segments of
+/// `path` already lead to (documents decoded in Rust walk them before
+/// building python objects, so only the subtree found is built).
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn dictor_from(_py: Python,
+    data: &PyAny,
+    resolved: usize,
+    path: Option<PathArg>,
+    default: Option<PyObject>,
+    checknone: Option<bool>,
+    ignorecase: Option<bool>,
+    pathsep: Option<String>,
+    search: Option<String>,
+    rtype: Option<RType>,
+    rounding: Option<&str>,
+    strict: Option<bool>,
+    unit: Option<&str>,
+    tz: Option<&str>,
+    on_error: Option<&str>,
+    getattr: Option<bool>
 ) -> PyResult<Option<PyObject>> {
     let mut inner_object: &PyAny = pyo3::PyTryInto::try_into(data).unwrap();
-    let input: Input;
+    let mut input = Input{ segments: vec![], delimiter: None };
     let ignorecase = ignorecase.unwrap_or(false);
-    let mut found = false;
-    let return_type: ReturnType;
-
-    if rtype.is_some(){
-        return_type = rtype.unwrap().into();
-    }else{
-        return_type = ReturnType::NONE;
-    }
+    let getattr = getattr.unwrap_or(false);
+    let mut found = resolved > 0;
+    let conversion = Conversion::new(_py, rtype, rounding, strict, unit, tz, on_error)?;
     
     if path.is_none() && search.is_none(){
         return Ok(None)
     }
 
+    if let Some(path_arg) = &path{
+        input = path_arg.input(_py, pathsep)?;
+    }
+    let path = path.map(|path| path.text()).transpose()?;
+
     if path.is_some(){
-        let path = path.clone().unwrap();
-    
-        if let Some(delimiter) = pathsep{
-            input = Input::new(path, delimiter);
-    
-        }else{
-            input = match Input::try_from(path) {
-                Ok(input) => input,
-                Err(e) => Err(PyErr::new::<PyTypeError, _>(e.to_string()))?
+        for (segment_index, segment) in input.segments.iter().enumerate().skip(resolved){
+            let location = |found: &PyAny| Location {
+                path: path.as_deref(),
+                segments: &input.segments,
+                delimiter: input.delimiter(),
+                segment_index: Some(segment_index),
+                found_type: type_name(found),
+                ..Location::default()
             };
-        };
-        
-        let mut inner_item: Result<&PyAny, PyErr>;
-        
-        if !input.args.is_empty(){
-            
-            for mut arg in input.args.into_iter(){
-                if inner_object.is_instance_of::<PyDict>(){
-                    inner_object = inner_object.downcast::<PyDict>().unwrap();
-                } else if inner_object.is_instance_of::<PyList>(){
-                    inner_object = inner_object.downcast::<PyList>().unwrap();
-                } else{
-                    if let Some(default_resp) = default{
-                        return Ok(Some(default_resp));
-                    }else{
-                        return Ok(None)
-                    }
-                }
-
-                if ignorecase {
-                    if inner_object.is_instance_of::<PyDict>(){
-                        let inner_dict = inner_object.downcast::<PyDict>().unwrap();
-                        let cased_key= inner_dict.keys().iter()
-                        .filter(|k|{
-                            k.to_string().to_lowercase() == arg.to_lowercase()
-                        })
-                        .map(|k| k.to_string())
-                        .collect::<Vec<String>>();
-                        if let Some(key) = cased_key.first(){
-                            arg = key.to_owned();
-                        } else{
-                            if let Some(default_resp) = default{
-                                return Ok(Some(default_resp));
-                            } else{
-                                if checknone.is_some_and(|v|v){
-                                    return Err(PyErr::new::<PyValueError, _>("value not found for search path"));
-                                }
-                                return Ok(None);
-                            }
-                        }
-                    }
-                };
-                // if arg is int (as string) it means we are dealing with a list (or supposing it)
-                // due to the int arg
-                if let Ok(num_arg) = arg.parse::<i32>(){
-                    inner_item  = inner_object.get_item(num_arg);
-                    if inner_item.is_err(){
-                        inner_item = inner_object.get_item(arg);
-                    }
-                }
-                else{
-                    inner_item = inner_object.get_item(arg);
-                };
-                if let Ok(item) = inner_item{
+            let step = traverse::step(_py, inner_object, segment, ignorecase, getattr);
+            match step.outcome {
+                Outcome::Found(item) => {
                     inner_object = item;
                     found = true;
-                }else{
-                    if let Some(default_resp) = default{
-                        return Ok(Some(default_resp));
-                    }else if checknone.is_some_and(|v|v){
-                        return Err(PyErr::new::<PyValueError, _>("value not found for search path"));
-                    }else {
-                        return Ok(None);
-                    }
-                    
-                };
+                },
+                _ if default.is_some() => return Ok(default),
+                Outcome::NotContainer if checknone.is_some_and(|v|v) => {
+                    return Err(lookup_error(_py, location(inner_object), &step.arg(segment), Failure::NotContainer));
+                },
+                Outcome::Missing if checknone.is_some_and(|v|v) => {
+                    let failure = Failure::Missing { attribute: step.attribute, candidates: candidates(&step, inner_object) };
+                    return Err(lookup_error(_py, location(inner_object), &step.arg(segment), failure));
+                },
+                _ => return Ok(None)
             }
         }
     }
-    if search.is_some() &&  !inner_object.is_none(){
+    if let Some(search) = search.filter(|_| !inner_object.is_none()){
         let accumulator: Vec<PyAny> = vec![];
         let py_list_accumulator = PyList::new(_py, accumulator);
-        find_occurences(_py, search.unwrap().as_str(), inner_object, default.as_ref(), py_list_accumulator);
+        find_occurences(_py, search.as_str(), inner_object, default.as_ref(), py_list_accumulator);
         if py_list_accumulator.is_empty() && checknone.is_some_and(|v|v){
-            return Err(PyErr::new::<PyValueError, _>(format!("value not found for search path: {:?}", path)));
+            let location = Location { path: path.as_deref(), ..Location::default() };
+            return Err(location.error::<PathNotFound>(_py, format!("value not found for search key: {search}")));
         }else{
             return Ok(Some(py_list_accumulator.to_object(_py)));
         }
@@ -241,77 +300,151 @@ fn dictor(_py: Python,
         }
 
      
-    if !found && default.is_some(){
-        Ok(Some(default.unwrap()))
+    if let Some(default) = default.as_ref().filter(|_| !found){
+        Ok(Some(default.clone_ref(_py)))
     }else if !found && checknone.is_some_and(|v|v) && inner_object.is_none(){
-        return Err(PyValueError::new_err(format!("value not found for search path: {:?}", path)));
+        let location = Location { path: path.as_deref(), ..Location::default() };
+        Err(location.error::<PathNotFound>(_py, format!("value not found for search path: {}", path.as_deref().unwrap_or_default())))
 
     }else{
-        // cast to return type if no errors, keep original type otherwise
-        match return_type{
-            ReturnType::STRING =>{
-                // ignore if cannot cast and keep original format
-                let casted_matching_item = inner_object.str();
-                if casted_matching_item.is_ok(){
-                    inner_object = casted_matching_item.unwrap();
-                }
-               
-            },
-            ReturnType::INT =>{
-                let content_str: Result<String, PyErr> = inner_object.extract();
-                // All this nasty hack is to overcome the issue:
-                // https://github.com/PyO3/pyo3/issues/2221
-                if let Ok(content) = content_str{
-                    let numeric_content = content.parse::<usize>();
-                    if let Ok(num_inner_object) = numeric_content{
-                        let asd = PyFloat::new(_py, num_inner_object as f64);
-                        inner_object = asd.into();
-                    }
-                }
-            },
-            ReturnType::NONE=>{} // keep original value
-        };
-        Ok(Some(inner_object.into()))
+        let default = default.as_ref().map(|default| default.as_ref(_py));
+        conversion.apply(_py, inner_object, default, checknone.unwrap_or(false))
+            .map(|converted| converted.map(Into::into))
+            .map_err(|e| match e.is_instance_of::<ConversionError>(_py) {
+                true => Location {
+                    path: path.as_deref(),
+                    segments: &input.segments,
+                    delimiter: input.delimiter(),
+                    segment_index: None,
+                    found_type: type_name(inner_object),
+                    ..Location::default()
+                }.attach(_py, e),
+                false => e
+            })
     }
     
 }
 
 
-fn find_occurences(py: Python, target: &str, searchable: &PyAny, default: Option<&PyObject>, accumulator: &PyList){
-    if searchable.is_instance_of::<PyList>(){
-        let iter = searchable.iter().unwrap();
-        for maybe_element in iter {
-            if let Ok(element) = maybe_element{
-                find_occurences(py, &target, element, default, accumulator);
+/// Name of the type of `value`, as `type(value).__name__`.
+pub(crate) fn type_name(value: &PyAny) -> Option<String> {
+    value.get_type().name().ok().map(str::to_owned)
+}
 
-            }
+
+/// Why a path segment could not be looked up.
+pub(crate) enum Failure{
+    /// The value reached is not a container
+    NotContainer,
+    /// The key is missing, `candidates` being the keys (or public data
+    /// attributes) found instead
+    Missing{ attribute: bool, candidates: Vec<String> }
+}
+
+
+/// Error raised when `arg` cannot be looked up in the value reached at
+/// `location`: a `TypeMismatch` when it is not a container, otherwise a
+/// `PathNotFound` suggesting the candidates `arg` was likely meant to be.
+pub(crate) fn lookup_error(py: Python, mut location: Location, arg: &str, failure: Failure) -> PyErr {
+    let path = location.path.unwrap_or_default();
+    let found_type = location.found_type.as_deref().unwrap_or("?");
+    let (attribute, candidates) = match failure {
+        Failure::NotContainer => {
+            let message = format!("value not found for search path: {path} (cannot look {arg:?} up in {found_type})");
+            return location.error::<TypeMismatch>(py, message)
+        },
+        Failure::Missing { attribute, candidates } => (attribute, candidates)
+    };
+    let mut message = match attribute {
+        true => format!("value not found for search path: {path} (no data attribute {arg:?} on {found_type}"),
+        false => format!("value not found for search path: {path} (no {arg:?} in {found_type}")
+    };
+    location.suggestions = suggest::suggestions(arg, candidates);
+    let quoted: Vec<String> = location.suggestions.iter().map(|suggestion| format!("{suggestion:?}")).collect();
+    match quoted.as_slice() {
+        [] => {},
+        [suggestion] => message.push_str(&format!(", did you mean {suggestion}?")),
+        [suggestions @ .., last] => message.push_str(&format!(", did you mean {} or {last}?", suggestions.join(", ")))
+    }
+    message.push(')');
+    location.error::<PathNotFound>(py, message)
+}
+
+
+/// Keys (or public data attributes) of `container` the key `step` looked up
+/// was likely meant to be.
+fn candidates(step: &traverse::Step, container: &PyAny) -> Vec<String> {
+    if step.attribute{
+        container.dir().iter()
+            .map(|name| name.to_string())
+            .filter(|name| !name.starts_with('_') && container.getattr(name.as_str()).is_ok_and(|item| !item.is_callable()))
+            .collect()
+    }else if traverse::is_mapping(container){
+        traverse::mapping_keys(container).into_iter().map(|key| key.to_string()).collect()
+    }else{
+        vec![]
+    }
+}
+
+
+/// Reads the text of a document given either as its content (`str` or
+/// `bytes`) or as a path (`os.PathLike`, or a single line `str` naming an
+/// existing file).
+#[cfg(any(feature = "yaml", feature = "toml", feature = "xml"))]
+pub(crate) fn read_text_or_path(source: &PyAny) -> PyResult<String> {
+    if let Ok(text) = source.downcast::<PyString>(){
+        let text = text.to_str()?;
+        let as_path = std::path::Path::new(text);
+        if !text.contains('\n') && as_path.is_file(){
+            return Ok(std::fs::read_to_string(as_path)?)
         }
-    }else if searchable.is_instance_of::<PyDict>(){
-        
-        let inner_dict = searchable.downcast::<PyDict>().unwrap();
-        for key in inner_dict.keys(){
-           
-            if let Some(matching_item) = inner_dict.get_item(key){
-                if key.to_string() == target{
-                    let obj_type = matching_item.get_type();
-                    let bool_type = py.get_type::<PyBool>();
-                    let str_type = py.get_type::<PyString>();
-                    if obj_type.is(bool_type){
-                        accumulator.append(matching_item).unwrap();
-                    }else if obj_type.is(str_type) {
-                        accumulator.append(matching_item).unwrap();
-                    }else if default.is_some(){
-                        accumulator.append(default).unwrap();
-                    }else{
-                        accumulator.append(matching_item).unwrap();
-                    }
-                }else if matching_item.is_instance_of::<PyDict>() ||
-                matching_item.is_instance_of::<PyList>(){
-                    find_occurences(py, target, matching_item, default, accumulator)
-                }
+        return Ok(text.to_owned())
+    }
+    if let Ok(raw) = source.downcast::<pyo3::types::PyBytes>(){
+        return String::from_utf8(raw.as_bytes().to_vec()).map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))
+    }
+    let path: std::path::PathBuf = source.extract()?;
+    Ok(std::fs::read_to_string(path)?)
+}
 
+
+/// Bytes of a binary document given as `bytes` or `bytearray`, the latter
+/// being copied as python code may resize it meanwhile.
+#[cfg(any(feature = "msgpack", feature = "cbor"))]
+pub(crate) fn read_bytes(source: &PyAny) -> PyResult<std::borrow::Cow<'_, [u8]>> {
+    if let Ok(raw) = source.downcast::<pyo3::types::PyByteArray>(){
+        return Ok(raw.to_vec().into())
+    }
+    Ok(source.downcast::<pyo3::types::PyBytes>()?.as_bytes().into())
+}
+
+
+fn find_occurences(py: Python, target: &str, searchable: &PyAny, default: Option<&PyObject>, accumulator: &PyList){
+    if traverse::is_scalar(searchable){
+        return
+    }
+    if traverse::is_mapping(searchable){
+        for (key, matching_item) in traverse::mapping_items(searchable){
+            if key.to_string() == target{
+                let obj_type = matching_item.get_type();
+                let bool_type = py.get_type::<PyBool>();
+                let str_type = py.get_type::<PyString>();
+                if obj_type.is(bool_type) || obj_type.is(str_type){
+                    accumulator.append(matching_item).unwrap();
+                }else if default.is_some(){
+                    accumulator.append(default).unwrap();
+                }else{
+                    accumulator.append(matching_item).unwrap();
+                }
+            }else{
+                find_occurences(py, target, matching_item, default, accumulator)
             }
         }
+    }else if traverse::is_sequence(searchable){
+        let iter = searchable.iter().unwrap();
+        for element in iter.flatten() {
+            find_occurences(py, target, element, default, accumulator);
+        }
     }
 
 }
@@ -319,11 +452,54 @@ fn find_occurences(py: Python, target: &str, searchable: &PyAny, default: Option
 #[pymodule]
 pub fn dicto_r(_py: Python, m: &PyModule) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(dictor, _py)?)?;
+    m.add("DictorError", _py.get_type::<errors::DictorError>())?;
+    m.add("PathNotFound", _py.get_type::<PathNotFound>())?;
+    m.add("TypeMismatch", _py.get_type::<TypeMismatch>())?;
+    m.add("ConversionError", _py.get_type::<ConversionError>())?;
+    m.add("PathSyntaxError", _py.get_type::<errors::PathSyntaxError>())?;
+    m.add("ExtractionError", _py.get_type::<errors::ExtractionError>())?;
+    m.add_function(wrap_pyfunction!(traverse::explain, _py)?)?;
+    m.add_function(wrap_pyfunction!(extract::extract, _py)?)?;
+    m.add_function(wrap_pyfunction!(json::dictor_json, _py)?)?;
+    m.add_function(wrap_pyfunction!(ndjson::scan_ndjson, _py)?)?;
+    m.add_function(wrap_pyfunction!(schema::validate, _py)?)?;
+    m.add_function(wrap_pyfunction!(infer::infer_schema, _py)?)?;
+    m.add_class::<ndjson::NdjsonScan>()?;
+    m.add_class::<json_file::JsonFile>()?;
+    m.add_class::<document::Dictor>()?;
+    #[cfg(feature = "yaml")]
+    {
+        m.add_function(wrap_pyfunction!(yaml::load_yaml, _py)?)?;
+        m.add_function(wrap_pyfunction!(yaml::dictor_yaml, _py)?)?;
+    }
+    #[cfg(feature = "toml")]
+    {
+        m.add_function(wrap_pyfunction!(toml::load_toml, _py)?)?;
+        m.add_function(wrap_pyfunction!(toml::dictor_toml, _py)?)?;
+    }
+    #[cfg(feature = "msgpack")]
+    {
+        m.add_function(wrap_pyfunction!(msgpack::load_msgpack, _py)?)?;
+        m.add_function(wrap_pyfunction!(msgpack::dictor_msgpack, _py)?)?;
+    }
+    #[cfg(feature = "cbor")]
+    {
+        m.add_function(wrap_pyfunction!(cbor::load_cbor, _py)?)?;
+        m.add_function(wrap_pyfunction!(cbor::dictor_cbor, _py)?)?;
+    }
+    #[cfg(feature = "xml")]
+    {
+        m.add_function(wrap_pyfunction!(xml::load_xml, _py)?)?;
+        m.add_function(wrap_pyfunction!(xml::dictor_xml, _py)?)?;
+    }
     Ok(())
 }
 
 
 #[cfg(test)]
+// the original tests are kept as they were written
+#[allow(unused_must_use, unused_variables, non_snake_case, clippy::needless_borrow,
+    clippy::needless_late_init, clippy::useless_format)]
 mod tests {
     use pyo3::types::{PyDict, PyList};
     use pyo3::Python;
@@ -346,7 +522,7 @@ mod tests {
             let default = default.to_object(py);
             let res: Result<Option<pyo3::prelude::Py<PyAny>>, PyErr> = dictor(py, list_dict, None,
                  Some(default),None, 
-                None, None, Some("name".to_string()), None);
+                None, None, Some("name".to_string()), None, None, None, None, None, None, None);
             let expected = PyList::new(py,vec!["spaceballs", "gone with the wind", "titanic", "pepe"]);
             let content = res.unwrap().unwrap();
             let content: &PyList = content.downcast(py).unwrap();
@@ -395,9 +571,9 @@ mod tests {
             let default = PyString::new(py, "pepe");
             let default = default.to_object(py);
             let res = dictor(py, dict, 
-                Some("terminator.2.terminator 3.preview".to_owned()),
+                Some("terminator.2.terminator 3.preview".into()),
                 Some(default),None, 
-                None, None, None, None);
+                None, None, None, None, None, None, None, None, None, None);
             let content = res.unwrap().unwrap();
             assert!(content.is_none(py))
 
@@ -416,9 +592,9 @@ mod tests {
                     }", None, None).unwrap(); 
 
             let res = dictor(py, dict, 
-                Some("year".to_owned()),
+                Some("year".into()),
                 None,None, 
-                None, None, None, Some("str".into())).unwrap();
+                None, None, None, Some("str".into()), None, None, None, None, None, None).unwrap();
             let content = res.unwrap();
             let content = content.downcast::<PyString>(py).unwrap();
             let expected_content = PyString::new(py, "1983");
@@ -437,9 +613,9 @@ mod tests {
                     }", None, None).unwrap(); 
 
             let res = dictor(py, dict, 
-                Some("year".to_owned()),
+                Some("year".into()),
                 None,None, 
-                None, None, None, Some("int".into())).unwrap();
+                None, None, None, Some("int".into()), None, None, None, None, None, None).unwrap();
             let content = res.unwrap();
             let content: usize = content.extract(py).unwrap();
             assert!(content == 1987)
@@ -457,12 +633,13 @@ mod tests {
                     }", None, None).unwrap(); 
 
             let res = dictor(py, dict, 
-                Some("year".to_owned()),
+                Some("year".into()),
                 None,None, 
-                None, None, None, Some("int".into())).unwrap();
+                None, None, None, Some("int".into()), None, None, None, None, None, None).unwrap();
             let content = res.unwrap();
-            let content: f32 = content.extract(py).unwrap();
-            assert!(content == 1987.0)
+            assert!(content.as_ref(py).is_instance_of::<pyo3::types::PyLong>());
+            let content: usize = content.extract(py).unwrap();
+            assert!(content == 1987)
             });
     }
  
@@ -519,7 +696,7 @@ mod tests {
                     ('titanic', 'comedy', None), \
                 ]]", None, None).unwrap();
             let res = dictor(py, list_dict, None, None,None, 
-                None, None, Some("name".to_string()), None);
+                None, None, Some("name".to_string()), None, None, None, None, None, None, None);
             let expected = PyList::new(py,vec!["spaceballs", "gone with the wind", "titanic", "titanic"]);
             let content = res.unwrap().unwrap();
             assert!(expected.compare(content).is_ok());
@@ -537,7 +714,7 @@ mod tests {
             let res = dictor(
                 py, dict, Some("item.4".into()), 
                 None, None, 
-                None, None, None, None);
+                None, None, None, None, None, None, None, None, None, None);
             assert!(res.unwrap().is_none());
 
             let dict2 = PyDict::new(py);
@@ -546,7 +723,7 @@ mod tests {
             let res = dictor(
                 py, dict, Some("other_item.4".into()), 
                 None, None, 
-                None, None, None, None).unwrap();
+                None, None, None, None, None, None, None, None, None, None).unwrap();
             assert_eq!(res.unwrap().to_string() , "found".to_string());
 
          
@@ -575,7 +752,7 @@ mod tests {
             let res = dictor(py, dict, 
                 None,
                 None,None, 
-                None, None, Some("foods".into()), None).unwrap();
+                None, None, Some("foods".into()), None, None, None, None, None, None, None).unwrap();
             let content = res.unwrap();
             // I have no idea how to convert this object but from python's side
             // it runs Ok
@@ -597,7 +774,7 @@ mod tests {
             let res = dictor(py, dict, 
                 Some(r"dirty\.harry.genre".into()),
                 None,None, 
-                None, None, None, None).unwrap();
+                None, None, None, None, None, None, None, None, None, None).unwrap();
             let content = res.unwrap();
             assert_eq!(content.to_string(), "romance");
 
@@ -613,8 +790,8 @@ mod tests {
             dict2.set_item("aLGO", "found");
             dict.set_item("oTRo", dict2);
             
-            let res = dictor(py, dict, Some("otro.algo".to_string()),
-             None, None,Some(true), None, None, None);
+            let res = dictor(py, dict, Some("otro.algo".into()),
+             None, None,Some(true), None, None, None, None, None, None, None, None, None);
             assert_eq!(res.unwrap().to_object(py).to_string(), "found".to_string());
         });
     }
@@ -629,8 +806,8 @@ mod tests {
             dict.set_item("oTRo", dict2);
             let default = PyString::new(py, "replaced");
             let default = default.to_object(py);
-            let res = dictor(py, dict, Some("otro.nonexistent".to_string()),
-             Some(default), None,Some(true), None, None, None);
+            let res = dictor(py, dict, Some("otro.nonexistent".into()),
+             Some(default), None,Some(true), None, None, None, None, None, None, None, None, None);
             assert_eq!(res.unwrap().to_object(py).to_string(), "replaced".to_string());
         });
     }
@@ -645,8 +822,8 @@ mod tests {
             let default_vec: Vec<String> = Vec::new();
             let default = PyList::new(py, default_vec);
             let default = default.to_object(py);
-            let res = dictor(py, dict, Some("otro.nonexistent".to_string()),
-             Some(default), None,Some(true), None, None, None);
+            let res = dictor(py, dict, Some("otro.nonexistent".into()),
+             Some(default), None,Some(true), None, None, None, None, None, None, None, None, None);
             let content = res.unwrap().to_object(py);
             let empty_list = content.downcast::<PyList>(py).unwrap();
             let empty_list: Vec<String> = empty_list.extract().unwrap();
@@ -666,8 +843,8 @@ mod tests {
             dict3.set_item("some_key", "value_3").unwrap();
             let list: &PyList = PyList::new(py, vec![dict1, dict2, dict3]);
         
-            let res = dictor(py, list, Some("otro.algo".to_string()),
-             None, None,Some(true), None, Some("some_key".to_string()), None);
+            let res = dictor(py, list, Some("otro.algo".into()),
+             None, None,Some(true), None, Some("some_key".to_string()), None, None, None, None, None, None, None);
             let content = res.unwrap();
             assert!(content.is_none());
         });
@@ -688,9 +865,95 @@ mod tests {
                     ('titanic', 'comedy', None), \
                 ]]", None, None).unwrap();
             let res = dictor(py, list_dict, 
-                Some("8.sarasa".to_owned()), None, Some(true), 
-                None, None, None, None);
+                Some("8.sarasa".into()), None, Some(true), 
+                None, None, None, None, None, None, None, None, None, None);
             assert!(res.is_err())
         });
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_brackets_and_slices(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let dict = py.eval("{'items': [{'n': 1}, {'n': 2}, {'n': 3}], 'a.b': {'c': 'd'}}", None, None).unwrap();
+            let lookup = |path: &str| dictor(py, dict, Some(path.into()),
+             None, None, None, None, None, None, None, None, None, None, None, None);
+            assert_eq!(lookup("items[1:].0.n").unwrap().unwrap().to_string(), "2");
+            assert_eq!(lookup(r#"["a.b"].c"#).unwrap().unwrap().to_string(), "d");
+            assert!(lookup("a.b[1:]").unwrap().is_none());
+            let e = lookup("items[1:x]").unwrap_err();
+            assert!(e.is_instance_of::<errors::PathSyntaxError>(py));
+            assert_eq!(e.value(py).getattr("offset").unwrap().extract::<usize>().unwrap(), 8);
+        });
+    }
+
+    #[test]
+    fn test_typed_keys(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let dict = py.eval("{'a': {3: {('x', 'y'): 'tuple'}}, 'b': {'3': 'str'}, 'items': ['z']}", None, None).unwrap();
+            let lookup = |path: PathArg| dictor(py, dict, Some(path),
+             None, Some(true), None, None, None, None, None, None, None, None, None, None);
+            let keys = py.eval("['a', 3, ('x', 'y')]", None, None).unwrap();
+            assert_eq!(lookup(PathArg::Keys(keys)).unwrap().unwrap().to_string(), "tuple");
+            let keys = py.eval("('items', -1)", None, None).unwrap();
+            assert_eq!(lookup(PathArg::Keys(keys)).unwrap().unwrap().to_string(), "z");
+            assert_eq!(lookup("b.3".into()).unwrap().unwrap().to_string(), "str");
+            assert!(lookup("b.#3".into()).unwrap_err().is_instance_of::<PathNotFound>(py));
+            assert!(lookup("a.#3".into()).unwrap().unwrap().as_ref(py).is_instance_of::<PyDict>());
+            let keys = py.eval("['a', 3, ('z',)]", None, None).unwrap();
+            let e = lookup(PathArg::Keys(keys)).unwrap_err();
+            assert_eq!(e.value(py).getattr("resolved_path").unwrap().to_string(), "a.#3");
+            let e = lookup(PathArg::Keys(py.eval("7", None, None).unwrap())).unwrap_err();
+            assert!(e.is_instance_of::<pyo3::exceptions::PyTypeError>(py));
+        });
+    }
+
+    #[test]
+    fn test_error_details(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let dict = py.eval("{'a': {'b': [1, {'c': 'x'}]}}", None, None).unwrap();
+            let lookup = |path: &str, rtype: Option<&str>| dictor(py, dict, Some(path.into()),
+             None, Some(true), None, None, None, rtype.map(Into::into), None, Some(true), None, None, None, None).unwrap_err();
+
+            let e = lookup("a.b.1.d", None);
+            assert!(e.is_instance_of::<PathNotFound>(py));
+            assert_eq!(e.value(py).to_string(), r#"value not found for search path: a.b.1.d (no "d" in dict)"#);
+            assert_eq!(e.value(py).getattr("resolved_path").unwrap().to_string(), "a.b.1");
+
+            let e = lookup("A.b", None);
+            assert_eq!(e.value(py).to_string(), r#"value not found for search path: A.b (no "A" in dict, did you mean "a"?)"#);
+            assert_eq!(e.value(py).getattr("suggestions").unwrap().extract::<Vec<String>>().unwrap(), ["a"]);
+
+            let e = lookup("a.b.0.z", None);
+            assert!(e.is_instance_of::<TypeMismatch>(py));
+            assert_eq!(e.value(py).getattr("found_type").unwrap().to_string(), "int");
+            assert_eq!(e.value(py).getattr("segment_index").unwrap().extract::<usize>().unwrap(), 3);
+
+            let e = lookup("a.b.1.c", Some("int"));
+            assert!(e.is_instance_of::<ConversionError>(py));
+            assert_eq!(e.value(py).getattr("resolved_path").unwrap().to_string(), "a.b.1.c");
+        });
+    }
+
+    #[test]
+    fn test_bytes_key(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let dict = py.eval("{b'raw': {'name': 'found'}, 'other': [{b'name': 'nested'}]}", None, None).unwrap();
+            let res = dictor(py, dict, Some("raw.name".into()),
+             None, None, None, None, None, None, None, None, None, None, None, None);
+            assert!(res.unwrap().is_none());
+            let keys = py.eval("[b'raw', 'name']", None, None).unwrap();
+            let res = dictor(py, dict, Some(PathArg::Keys(keys)),
+             None, None, None, None, None, None, None, None, None, None, None, None);
+            assert_eq!(res.unwrap().unwrap().to_string(), "found");
+
+            let res = dictor(py, dict, None,
+             None, None, None, None, Some("name".into()), None, None, None, None, None, None, None);
+            let content: Vec<String> = res.unwrap().unwrap().extract(py).unwrap();
+            assert_eq!(content, vec!["found"]);
+        });
+    }
+}
@@ -0,0 +1,208 @@
+//! MessagePack documents (behind the `msgpack` feature).
+//!
+//! `dictor_msgpack` walks the path over the decoded values and only builds
+//! python objects for the subtree found. Map keys keep their MessagePack
+//! type, so integer keys are addressed as `"42"` (as in `dictor`) and binary
+//! keys as `bytes` in lists of keys. Extension values load as `(type, data)`
+//! tuples.
+
+use pyo3::exceptions::PyValueError;
+use pyo3::types::{PyBytes, PyDict, PyList, PyTuple};
+use pyo3::{pyfunction, PyAny, PyObject, PyResult, Python, ToPyObject};
+use rmpv::Value;
+
+use crate::tree::{self, Key, Node, Tree};
+use crate::{dictor_from, read_bytes, PathArg, RType};
+
+
+impl Tree for Value {
+    fn node(&self) -> Node<'_, Self> {
+        match self {
+            Value::Map(map) => Node::Map(map),
+            Value::Array(list) => Node::Array(list),
+            _ => Node::Scalar
+        }
+    }
+
+    fn key(&self) -> Key<'_> {
+        match self {
+            Value::String(s) => s.as_str().map_or(Key::Other, Key::Str),
+            Value::Integer(i) => i.as_i64().map(i128::from).or(i.as_u64().map(i128::from)).map_or(Key::Other, Key::Int),
+            _ => Key::Other
+        }
+    }
+}
+
+
+/// Converts `value`, building tuples instead of lists for keys so that
+/// array keys remain hashable.
+fn to_py(py: Python, value: &Value, as_key: bool) -> PyResult<PyObject> {
+    Ok(match value {
+        Value::Nil => py.None(),
+        Value::Boolean(b) => b.to_object(py),
+        Value::Integer(i) => {
+            if let Some(i) = i.as_i64(){
+                i.to_object(py)
+            }else{
+                i.as_u64().to_object(py)
+            }
+        },
+        Value::F32(f) => f.to_object(py),
+        Value::F64(f) => f.to_object(py),
+        Value::String(s) => match s.as_str() {
+            Some(s) => s.to_object(py),
+            // invalid UTF-8 is kept as raw bytes
+            None => PyBytes::new(py, s.as_bytes()).to_object(py)
+        },
+        Value::Binary(b) => PyBytes::new(py, b).to_object(py),
+        Value::Array(list) => {
+            let items = list.iter().map(|item| to_py(py, item, as_key)).collect::<PyResult<Vec<_>>>()?;
+            if as_key{
+                PyTuple::new(py, items).to_object(py)
+            }else{
+                PyList::new(py, items).to_object(py)
+            }
+        },
+        Value::Map(map) => {
+            let dict = PyDict::new(py);
+            for (key, item) in map{
+                dict.set_item(to_py(py, key, true)?, to_py(py, item, false)?)?;
+            }
+            dict.to_object(py)
+        },
+        Value::Ext(code, data) => (code, PyBytes::new(py, data)).to_object(py)
+    })
+}
+
+
+fn decode(source: &PyAny) -> PyResult<Value> {
+    let bytes = read_bytes(source)?;
+    let mut reader = &bytes[..];
+    let value = rmpv::decode::read_value(&mut reader).map_err(|e| PyValueError::new_err(format!("invalid MessagePack: {e}")))?;
+    if !reader.is_empty(){
+        return Err(PyValueError::new_err(format!("invalid MessagePack: {} trailing bytes", reader.len())))
+    }
+    Ok(value)
+}
+
+
+/// Decodes a MessagePack document into python objects.
+#[pyfunction]
+pub(crate) fn load_msgpack(py: Python, source: &PyAny) -> PyResult<PyObject> {
+    to_py(py, &decode(source)?, false)
+}
+
+
+/// Same as `dictor` but takes MessagePack encoded bytes.
+#[pyfunction]
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn dictor_msgpack(py: Python,
+    source: &PyAny,
+    path: Option<PathArg>,
+    default: Option<PyObject>,
+    checknone: Option<bool>,
+    ignorecase: Option<bool>,
+    pathsep: Option<String>,
+    search: Option<String>,
+    rtype: Option<RType>,
+    rounding: Option<&str>,
+    strict: Option<bool>,
+    unit: Option<&str>,
+    tz: Option<&str>,
+    on_error: Option<&str>
+) -> PyResult<Option<PyObject>> {
+    let value = decode(source)?;
+    let (resolved, found) = match &path {
+        Some(path) => tree::resolve(&value, path.input(py, pathsep.clone())?.segments(), ignorecase.unwrap_or(false)),
+        None => (0, &value)
+    };
+    let data = to_py(py, found, false)?;
+    dictor_from(py, data.as_ref(py), resolved, path, default, checknone, ignorecase, pathsep, search, rtype, rounding, strict, unit, tz, on_error, None)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(value: &Value) -> Vec<u8> {
+        let mut bytes = vec![];
+        rmpv::encode::write_value(&mut bytes, value).unwrap();
+        bytes
+    }
+
+    fn document() -> Vec<u8> {
+        encode(&Value::Map(vec![
+            (Value::from("users"), Value::Array(vec![
+                Value::Map(vec![(Value::from("name"), Value::from("pepe"))]),
+                Value::Map(vec![(Value::from("name"), Value::from("pipo"))]),
+            ])),
+            (Value::from(42), Value::from("int key")),
+            (Value::Binary(b"raw".to_vec()), Value::Map(vec![(Value::from("name"), Value::from("bytes key"))])),
+        ]))
+    }
+
+    #[test]
+    fn non_string_keys(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let source = PyBytes::new(py, &document());
+            let res = dictor_msgpack(py, source, Some("42".into()), None, None,
+                None, None, None, None, None, None, None, None, None).unwrap().unwrap();
+            assert_eq!(res.extract::<String>(py).unwrap(), "int key");
+            let keys = py.eval("[b'raw', 'name']", None, None).unwrap();
+            let res = dictor_msgpack(py, source, Some(PathArg::Keys(keys)), None, None,
+                None, None, None, None, None, None, None, None, None).unwrap().unwrap();
+            assert_eq!(res.extract::<String>(py).unwrap(), "bytes key");
+            let res = dictor_msgpack(py, source, Some("raw.name".into()), None, None,
+                None, None, None, None, None, None, None, None, None).unwrap();
+            assert!(res.is_none());
+        });
+    }
+
+    #[test]
+    fn paths_resolve_before_building_python_objects(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let value = rmpv::decode::read_value(&mut &document()[..]).unwrap();
+            let resolved = |path: &str, ignorecase: bool| {
+                let input = crate::Input::from_path(path.into(), None).unwrap();
+                tree::resolve(&value, input.segments(), ignorecase).0
+            };
+            assert_eq!(resolved("users.-1.name", false), 3);
+            assert_eq!(resolved("USERS.0.Name", true), 3);
+            assert_eq!(resolved("users.2.name", false), 1);
+            // integer keys are left to python in maps with other (binary) keys
+            assert_eq!(resolved("42", false), 0);
+            let source = PyBytes::new(py, &document());
+            let e = dictor_msgpack(py, source, Some("users.0.nme".into()), None, Some(true),
+                None, None, None, None, None, None, None, None, None).unwrap_err();
+            assert_eq!(e.value(py).getattr("resolved_path").unwrap().to_string(), "users.0");
+            assert_eq!(e.value(py).getattr("suggestions").unwrap().to_string(), "['name']");
+        });
+    }
+
+    #[test]
+    fn path_and_search(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let source = PyBytes::new(py, &document());
+            let res = dictor_msgpack(py, source, Some("users.1.name".into()), None, None,
+                None, None, None, None, None, None, None, None, None).unwrap().unwrap();
+            assert_eq!(res.extract::<String>(py).unwrap(), "pipo");
+            let res = dictor_msgpack(py, source, None, None, None,
+                None, None, Some("name".into()), None, None, None, None, None, None).unwrap().unwrap();
+            assert_eq!(res.extract::<Vec<String>>(py).unwrap(), vec!["pepe", "pipo", "bytes key"]);
+        });
+    }
+
+    #[test]
+    fn trailing_bytes_are_rejected(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let mut bytes = document();
+            bytes.push(0xc0);
+            assert!(load_msgpack(py, PyBytes::new(py, &bytes)).is_err());
+        });
+    }
+}
@@ -0,0 +1,335 @@
+//! Streaming lookups over newline-delimited JSON (NDJSON) files.
+//!
+//! Every record is skimmed with the same path resolution used by
+//! `dictor_json`, so only the extracted values are built as python objects.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+use pyo3::exceptions::PyValueError;
+use pyo3::types::{PyBytes, PyDict, PyString, PyTuple};
+use pyo3::{pyclass, pyfunction, pymethods, PyAny, PyErr, PyObject, PyRef, PyRefMut, PyResult, Python, ToPyObject};
+use serde_json::value::RawValue;
+use serde_json::Value;
+
+use crate::json::{path_keys, resolve_raw, to_py};
+use crate::{Conversion, RType};
+
+
+enum Source{
+    File(BufReader<File>),
+    /// Any python object with a `readline` method returning `str` or `bytes`
+    FileObj(PyObject)
+}
+
+impl Source {
+    /// Reads the next line into `buf`, returning `false` once exhausted.
+    fn read_line(&mut self, py: Python, buf: &mut Vec<u8>) -> PyResult<bool> {
+        buf.clear();
+        match self {
+            Source::File(reader) => Ok(reader.read_until(b'\n', buf)? > 0),
+            Source::FileObj(fileobj) => {
+                let line = fileobj.call_method0(py, "readline")?;
+                let line = line.as_ref(py);
+                if let Ok(line) = line.downcast::<PyBytes>(){
+                    buf.extend_from_slice(line.as_bytes());
+                }else{
+                    buf.extend_from_slice(line.downcast::<PyString>()?.to_str()?.as_bytes());
+                }
+                Ok(!buf.is_empty())
+            }
+        }
+    }
+}
+
+
+/// How extracted values are handed back for every record.
+enum Shape{
+    /// A single path: the bare value
+    Single,
+    /// A list or tuple of paths: a tuple of values
+    Tuple,
+    /// A `{name: path}` mapping: a dict with the same names
+    Dict(Vec<PyObject>)
+}
+
+
+#[derive(Clone, Copy)]
+enum OnError{
+    Raise,
+    Skip,
+    Report
+}
+
+impl TryFrom<&str> for OnError{
+    type Error = PyErr;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "raise" => Ok(OnError::Raise),
+            "skip" => Ok(OnError::Skip),
+            "report" => Ok(OnError::Report),
+            _ => Err(PyValueError::new_err(format!("invalid errors mode: {value} (expected raise, skip or report)")))
+        }
+    }
+}
+
+
+/// Iterator over the values extracted from each record of an NDJSON stream.
+///
+/// Malformed lines raise, are skipped or are recorded in `errors` as
+/// `(line_number, message)` tuples, depending on the `errors` mode.
+#[pyclass(module = "dicto_r")]
+pub(crate) struct NdjsonScan{
+    source: Source,
+    shape: Shape,
+    paths: Vec<Vec<String>>,
+    filters: Vec<(Vec<String>, PyObject)>,
+    default: Option<PyObject>,
+    ignorecase: bool,
+    conversion: Conversion,
+    on_error: OnError,
+    line_number: usize,
+    buf: Vec<u8>,
+    #[pyo3(get)]
+    errors: Vec<(usize, String)>
+}
+
+impl NdjsonScan {
+    /// Extracts the value at `args`. Malformed JSON (`Ok(Err(_))`) is kept
+    /// apart from values that cannot be converted in strict mode.
+    fn extract(&self, py: Python, record: &RawValue, args: &[String]) -> PyResult<Result<PyObject, serde_json::Error>> {
+        let found = resolve_raw(record, args, self.ignorecase)
+            .and_then(|item| item.ok().map(|item| serde_json::from_str::<Value>(item.get())).transpose());
+        let value = match found {
+            Ok(Some(value)) => value,
+            Ok(None) => return Ok(Ok(self.default.as_ref().map_or_else(|| py.None(), |d| d.clone_ref(py)))),
+            Err(e) => return Ok(Err(e))
+        };
+        let item = to_py(py, &value);
+        let default = self.default.as_ref().map(|default| default.as_ref(py));
+        Ok(Ok(self.conversion.apply(py, item.as_ref(py), default, false)?.map_or_else(|| py.None(), Into::into)))
+    }
+
+    /// Whether `record` holds the expected value (or satisfies the predicate)
+    /// for every filter path.
+    fn matches(&self, py: Python, record: &RawValue) -> PyResult<bool> {
+        for (args, expected) in self.filters.iter(){
+            let value = match resolve_raw(record, args, self.ignorecase).map_err(|e| PyValueError::new_err(e.to_string()))? {
+                Ok(item) => serde_json::from_str::<Value>(item.get()).map_err(|e| PyValueError::new_err(e.to_string()))?,
+                Err(_) => return Ok(false)
+            };
+            let value = to_py(py, &value);
+            let expected = expected.as_ref(py);
+            let matched = if expected.is_callable() && !expected.is_instance_of::<PyString>(){
+                expected.call1((value,))?.is_true()?
+            }else{
+                expected.eq(value)?
+            };
+            if !matched{
+                return Ok(false)
+            }
+        }
+        Ok(true)
+    }
+
+    /// Extracts the values of a single line. Python errors (raised by filter
+    /// callables, for instance) are kept apart from malformed JSON.
+    fn record(&self, py: Python, line: &[u8]) -> PyResult<Result<Option<PyObject>, serde_json::Error>> {
+        let record: &RawValue = match serde_json::from_slice(line) {
+            Ok(record) => record,
+            Err(e) => return Ok(Err(e))
+        };
+        if !self.matches(py, record)?{
+            return Ok(Ok(None))
+        }
+        let mut values = Vec::with_capacity(self.paths.len());
+        for args in self.paths.iter(){
+            match self.extract(py, record, args)? {
+                Ok(value) => values.push(value),
+                Err(e) => return Ok(Err(e))
+            }
+        }
+        let values = match &self.shape {
+            Shape::Single => values.pop().unwrap_or_else(|| py.None()),
+            Shape::Tuple => PyTuple::new(py, values).to_object(py),
+            Shape::Dict(names) => {
+                let dict = PyDict::new(py);
+                for (name, value) in names.iter().zip(values){
+                    dict.set_item(name, value)?;
+                }
+                dict.to_object(py)
+            }
+        };
+        Ok(Ok(Some(values)))
+    }
+}
+
+#[pymethods]
+impl NdjsonScan {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(mut slf: PyRefMut<'_, Self>, py: Python) -> PyResult<Option<PyObject>> {
+        let mut buf = std::mem::take(&mut slf.buf);
+        let next = loop {
+            if !slf.source.read_line(py, &mut buf)?{
+                break None
+            }
+            slf.line_number += 1;
+            let line = buf.trim_ascii();
+            if line.is_empty(){
+                continue
+            }
+            let record = slf.record(py, line);
+            match record {
+                Ok(Ok(Some(values))) => break Some(values),
+                Ok(Ok(None)) => continue,
+                Err(e) => {
+                    slf.buf = buf;
+                    return Err(e)
+                },
+                Ok(Err(e)) => {
+                    let message = e.to_string();
+                    match slf.on_error {
+                        OnError::Raise => {
+                            slf.buf = buf;
+                            return Err(PyValueError::new_err(format!("line {}: {message}", slf.line_number)))
+                        },
+                        OnError::Report => {
+                            let line_number = slf.line_number;
+                            slf.errors.push((line_number, message));
+                        },
+                        OnError::Skip => {}
+                    }
+                }
+            }
+        };
+        slf.buf = buf;
+        Ok(next)
+    }
+}
+
+
+/// Streams an NDJSON file (a path or an object with a `readline` method) and
+/// yields the values found at `paths` for each record.
+///
+/// `paths` may be a single path (yields the bare value), a list or tuple of
+/// paths (yields tuples) or a `{name: path}` dict (yields dicts). `filter`
+/// is a `{path: expected}` dict; records are only yielded if every path holds
+/// the expected value, or the value satisfies it when it is a callable.
+#[pyfunction]
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn scan_ndjson(py: Python,
+    source: &PyAny,
+    paths: &PyAny,
+    filter: Option<&PyDict>,
+    default: Option<PyObject>,
+    ignorecase: Option<bool>,
+    pathsep: Option<String>,
+    rtype: Option<RType>,
+    rounding: Option<&str>,
+    strict: Option<bool>,
+    unit: Option<&str>,
+    tz: Option<&str>,
+    on_error: Option<&str>,
+    errors: Option<&str>
+) -> PyResult<NdjsonScan> {
+    let parse_path = |path: &PyAny| -> PyResult<Vec<String>> {
+        path_keys(py, &path.extract()?, pathsep.clone())
+    };
+
+    let source = if source.hasattr("readline")?{
+        Source::FileObj(source.to_object(py))
+    }else{
+        let file_path: std::path::PathBuf = source.extract()?;
+        Source::File(BufReader::new(File::open(file_path)?))
+    };
+
+    let (shape, paths) = if paths.is_instance_of::<PyString>(){
+        (Shape::Single, vec![parse_path(paths)?])
+    }else if let Ok(named_paths) = paths.downcast::<PyDict>(){
+        let mut names = vec![];
+        let mut parsed_paths = vec![];
+        for (name, path) in named_paths.iter(){
+            names.push(name.to_object(py));
+            parsed_paths.push(parse_path(path)?);
+        }
+        (Shape::Dict(names), parsed_paths)
+    }else{
+        let parsed_paths = paths.iter()?.map(|path| parse_path(path?)).collect::<PyResult<_>>()?;
+        (Shape::Tuple, parsed_paths)
+    };
+
+    let mut filters = vec![];
+    for (path, expected) in filter.into_iter().flat_map(|f| f.iter()){
+        filters.push((parse_path(path)?, expected.to_object(py)));
+    }
+
+    Ok(NdjsonScan {
+        source,
+        shape,
+        paths,
+        filters,
+        default,
+        ignorecase: ignorecase.unwrap_or(false),
+        conversion: Conversion::new(py, rtype, rounding, strict, unit, tz, on_error)?,
+        on_error: errors.unwrap_or("raise").try_into()?,
+        line_number: 0,
+        buf: vec![],
+        errors: vec![]
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use pyo3::types::PyList;
+
+    use super::*;
+
+    const RECORDS: &str = "{\"a\": {\"b\": 1}, \"lvl\": \"info\"}\n\n{\"a\": {\"b\": 2}, \"lvl\": \"error\"}\nnot json\n";
+
+    fn scan(py: Python, paths: &PyAny, filter: Option<&PyDict>, errors: &str) -> PyResult<Vec<PyObject>> {
+        let source = py.import("io").unwrap().getattr("StringIO").unwrap().call1((RECORDS,)).unwrap();
+        let scan = pyo3::Py::new(py, scan_ndjson(py, source, paths, filter, None, None, None, None, None, None, None, None, None, Some(errors))?).unwrap();
+        let mut values = vec![];
+        while let Some(value) = NdjsonScan::__next__(scan.borrow_mut(py), py)?{
+            values.push(value);
+        }
+        Ok(values)
+    }
+
+    #[test]
+    fn scan_tuples_skipping_malformed_lines(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let paths = PyList::new(py, ["a.b", "lvl"]);
+            let values = scan(py, paths, None, "skip").unwrap();
+            let values: Vec<(usize, String)> = values.iter().map(|v| v.extract(py).unwrap()).collect();
+            assert_eq!(values, vec![(1, "info".to_owned()), (2, "error".to_owned())]);
+        });
+    }
+
+    #[test]
+    fn scan_filtered_records(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let filter = PyDict::new(py);
+            filter.set_item("lvl", "error").unwrap();
+            let values = scan(py, PyString::new(py, "a.b"), Some(filter), "skip").unwrap();
+            let values: Vec<usize> = values.iter().map(|v| v.extract(py).unwrap()).collect();
+            assert_eq!(values, vec![2]);
+        });
+    }
+
+    #[test]
+    fn scan_raises_with_line_number(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let err = scan(py, PyString::new(py, "a.b"), None, "raise").unwrap_err();
+            assert!(err.value(py).to_string().starts_with("line 4:"));
+        });
+    }
+}
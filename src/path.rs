@@ -0,0 +1,435 @@
+//! Path syntax.
+//!
+//! A path is a list of segments separated by a delimiter (`pathsep`, or the
+//! first of `.` and `/` found in the path when not given). Besides plain
+//! keys, segments may be written in brackets right after the previous one:
+//!
+//! - `items[0]`, `items[-1]`: same as `items.0` and `items.-1`;
+//! - `["dotted.key"]`, `['dotted.key']`: keys holding any character;
+//! - `items[1:3]`, `items[::2]`: python slices of lists.
+//!
+//! Segments written `#42` (or `[#42]`) are integer keys, looked up as is.
+//! A backslash escapes the delimiter, brackets, `#` and itself
+//! (`dirty\.harry`, `\#42`).
+
+use std::fmt::Display;
+
+use pyo3::{PyAny, PyObject, PyResult};
+
+
+pub(crate) const DOT: &str = ".";
+pub(crate) const SLASH: &str = "/";
+
+
+/// A `[start:stop:step]` segment.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Slice{
+    pub start: Option<isize>,
+    pub stop: Option<isize>,
+    pub step: Option<isize>
+}
+
+impl Display for Slice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let bound = |bound: Option<isize>| bound.map(|bound| bound.to_string()).unwrap_or_default();
+        match self.step {
+            Some(step) => write!(f, "[{}:{}:{step}]", bound(self.start), bound(self.stop)),
+            None => write!(f, "[{}:{}]", bound(self.start), bound(self.stop))
+        }
+    }
+}
+
+
+/// A key given as a python object (a tuple, an enum...), looked up as is.
+#[derive(Clone, Debug)]
+pub struct RawKey{
+    pub object: PyObject,
+    /// `repr()` of the key, standing for it in paths written back
+    pub repr: String
+}
+
+impl RawKey {
+    pub fn new(key: &PyAny) -> PyResult<Self> {
+        Ok(Self { object: key.into(), repr: key.repr()?.to_str()?.to_owned() })
+    }
+}
+
+impl PartialEq for RawKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.object.is(&other.object)
+    }
+}
+
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Segment{
+    Key(String),
+    /// `#42`: an integer key
+    Int(i64),
+    Slice(Slice),
+    Raw(RawKey)
+}
+
+impl Segment {
+    /// The key segment written `key`, an integer key when it is `#` and an
+    /// integer (unless the `#` was escaped).
+    fn written(key: String, escaped: bool) -> Segment {
+        match key.strip_prefix('#').filter(|_| !escaped).map(str::parse::<i64>) {
+            Some(Ok(int)) => Segment::Int(int),
+            _ => Segment::Key(key)
+        }
+    }
+}
+
+impl Display for Segment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Segment::Key(key) => write!(f, "{key}"),
+            Segment::Int(int) => write!(f, "#{int}"),
+            Segment::Slice(slice) => write!(f, "{slice}"),
+            Segment::Raw(key) => write!(f, "{}", key.repr)
+        }
+    }
+}
+
+
+#[derive(Debug, PartialEq)]
+pub enum ParseErrorKind{
+    InvalidDelimiter(String),
+    DanglingEscape,
+    EmptySegment,
+    UnterminatedBracket,
+    BadSlice,
+    ExpectedDelimiter
+}
+
+impl Display for ParseErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseErrorKind::InvalidDelimiter(delimiter) => write!(f, "invalid delimiter {delimiter:?}"),
+            ParseErrorKind::DanglingEscape => write!(f, "dangling escape"),
+            ParseErrorKind::EmptySegment => write!(f, "empty segment"),
+            ParseErrorKind::UnterminatedBracket => write!(f, "unterminated bracket"),
+            ParseErrorKind::BadSlice => write!(f, "bad slice"),
+            ParseErrorKind::ExpectedDelimiter => write!(f, "expected a delimiter after ]")
+        }
+    }
+}
+
+
+/// A path that cannot be parsed, along with the offset (in characters) of
+/// the problem.
+#[derive(Debug)]
+pub struct ParseError{
+    pub path: String,
+    pub offset: usize,
+    pub kind: ParseErrorKind
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at offset {}\n    {}\n    {}^", self.kind, self.offset, self.path, " ".repeat(self.offset))
+    }
+}
+
+
+/// The delimiter a path given without `pathsep` uses: the first of `.` and
+/// `/` found outside brackets.
+pub(crate) fn sniff_delimiter(path: &str) -> Option<&'static str> {
+    let mut chars = path.chars();
+    let mut slash = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => { chars.next(); },
+            '[' => {
+                for c in chars.by_ref(){
+                    if c == ']'{
+                        break
+                    }
+                }
+            },
+            '.' => return Some(DOT),
+            '/' => slash = true,
+            _ => {}
+        }
+    }
+    slash.then_some(SLASH)
+}
+
+
+struct Parser<'a>{
+    path: &'a str,
+    chars: Vec<char>,
+    delimiter: Vec<char>,
+    pos: usize
+}
+
+impl Parser<'_> {
+    fn error(&self, offset: usize, kind: ParseErrorKind) -> ParseError {
+        ParseError { path: self.path.to_owned(), offset, kind }
+    }
+
+    fn at_delimiter(&self, pos: usize) -> bool {
+        !self.delimiter.is_empty() && self.chars[pos..].starts_with(&self.delimiter)
+    }
+
+    fn parse(mut self) -> Result<Vec<Segment>, ParseError> {
+        let mut segments = vec![];
+        let mut key = String::new();
+        // whether the segment being read has anything (a key or brackets)
+        let mut started = false;
+        let mut after_bracket = false;
+        // whether the key read starts with an escaped `#`
+        let mut escaped_hash = false;
+        while self.pos < self.chars.len(){
+            if self.at_delimiter(self.pos){
+                if !started{
+                    return Err(self.error(self.pos, ParseErrorKind::EmptySegment))
+                }
+                if !after_bracket{
+                    segments.push(Segment::written(std::mem::take(&mut key), escaped_hash));
+                }
+                self.pos += self.delimiter.len();
+                started = false;
+                after_bracket = false;
+                escaped_hash = false;
+                if self.pos == self.chars.len(){
+                    return Err(self.error(self.pos, ParseErrorKind::EmptySegment))
+                }
+                continue
+            }
+            match self.chars[self.pos] {
+                '[' => {
+                    if !key.is_empty(){
+                        segments.push(Segment::written(std::mem::take(&mut key), escaped_hash));
+                    }
+                    segments.push(self.bracket()?);
+                    started = true;
+                    after_bracket = true;
+                },
+                _ if after_bracket => return Err(self.error(self.pos, ParseErrorKind::ExpectedDelimiter)),
+                '\\' => {
+                    let escaped = self.pos + 1;
+                    if escaped == self.chars.len(){
+                        return Err(self.error(self.pos, ParseErrorKind::DanglingEscape))
+                    }
+                    if self.at_delimiter(escaped){
+                        key.extend(&self.delimiter);
+                        self.pos = escaped + self.delimiter.len();
+                    }else if matches!(self.chars[escaped], '[' | ']' | '\\' | '#'){
+                        escaped_hash |= key.is_empty() && self.chars[escaped] == '#';
+                        key.push(self.chars[escaped]);
+                        self.pos = escaped + 1;
+                    }else{
+                        key.push('\\');
+                        self.pos = escaped;
+                    }
+                    started = true;
+                },
+                c => {
+                    key.push(c);
+                    self.pos += 1;
+                    started = true;
+                }
+            }
+        }
+        if !after_bracket{
+            segments.push(Segment::written(key, escaped_hash));
+        }
+        Ok(segments)
+    }
+
+    /// Reads the bracket segment starting at the current position.
+    fn bracket(&mut self) -> Result<Segment, ParseError> {
+        let open = self.pos;
+        let unterminated = |parser: &Self| parser.error(open, ParseErrorKind::UnterminatedBracket);
+        let mut pos = open + 1;
+        if let Some(quote) = self.chars.get(pos).copied().filter(|c| *c == '"' || *c == '\''){
+            let mut key = String::new();
+            pos += 1;
+            loop {
+                match self.chars.get(pos) {
+                    None => return Err(unterminated(self)),
+                    Some('\\') => {
+                        key.push(*self.chars.get(pos + 1).ok_or_else(|| unterminated(self))?);
+                        pos += 2;
+                    },
+                    Some(c) if *c == quote => break,
+                    Some(c) => {
+                        key.push(*c);
+                        pos += 1;
+                    }
+                }
+            }
+            if self.chars.get(pos + 1) != Some(&']'){
+                return Err(unterminated(self))
+            }
+            self.pos = pos + 2;
+            return Ok(Segment::Key(key))
+        }
+        let close = self.chars[pos..].iter().position(|c| *c == ']').ok_or_else(|| unterminated(self))? + pos;
+        self.pos = close + 1;
+        let content: String = self.chars[pos..close].iter().collect();
+        if content.trim().is_empty(){
+            return Err(self.error(open, ParseErrorKind::EmptySegment))
+        }
+        if !content.contains(':'){
+            return Ok(Segment::written(content.trim().to_owned(), false))
+        }
+        let mut bounds = vec![];
+        for part in content.split(':'){
+            let bound = match part.trim() {
+                "" => None,
+                bound => Some(bound.parse::<isize>().map_err(|_| self.error(pos, ParseErrorKind::BadSlice))?)
+            };
+            bounds.push(bound);
+            pos += part.chars().count() + 1;
+        }
+        match bounds[..] {
+            [start, stop] => Ok(Segment::Slice(Slice { start, stop, step: None })),
+            [_, _, Some(0)] => Err(self.error(pos - 2, ParseErrorKind::BadSlice)),
+            [start, stop, step] => Ok(Segment::Slice(Slice { start, stop, step })),
+            _ => Err(self.error(open, ParseErrorKind::BadSlice))
+        }
+    }
+}
+
+
+/// Splits `path` into its segments, `delimiter` being `None` for paths made
+/// of a single key (and brackets).
+pub fn parse(path: &str, delimiter: Option<&str>) -> Result<Vec<Segment>, ParseError> {
+    if let Some(delimiter) = delimiter.filter(|delimiter| delimiter.is_empty() || delimiter.contains(['[', ']', '\\'])){
+        let kind = ParseErrorKind::InvalidDelimiter(delimiter.to_owned());
+        return Err(ParseError { path: path.to_owned(), offset: 0, kind })
+    }
+    // paths without brackets, escapes nor integer keys are only split (the
+    // parser reports their empty segments)
+    if !path.contains(['[', '\\', '#']){
+        match delimiter {
+            None => return Ok(vec![Segment::Key(path.to_owned())]),
+            Some(delimiter) if !path.split(delimiter).any(str::is_empty) => {
+                return Ok(path.split(delimiter).map(|key| Segment::Key(key.to_owned())).collect())
+            },
+            _ => {}
+        }
+    }
+    Parser {
+        path,
+        chars: path.chars().collect(),
+        delimiter: delimiter.unwrap_or_default().chars().collect(),
+        pos: 0
+    }.parse()
+}
+
+
+/// Writes `segments` back as a path, escaping what needs to be. Keys holding
+/// a separator other than `delimiter` are quoted in brackets, so the path is
+/// split back the same way when its delimiter is sniffed.
+pub fn render(segments: &[Segment], delimiter: &str) -> String {
+    let mut path = String::new();
+    for (pos, segment) in segments.iter().enumerate(){
+        match segment {
+            Segment::Key(key) if [DOT, SLASH].iter().any(|separator| *separator != delimiter && key.contains(separator)) => {
+                path.push_str("[\"");
+                for c in key.chars(){
+                    if matches!(c, '"' | '\\'){
+                        path.push('\\');
+                    }
+                    path.push(c);
+                }
+                path.push_str("\"]");
+            },
+            Segment::Key(key) => {
+                if pos > 0{
+                    path.push_str(delimiter);
+                }
+                let mut rest = key.as_str();
+                while let Some(c) = rest.chars().next() {
+                    if !delimiter.is_empty() && rest.starts_with(delimiter){
+                        path.push('\\');
+                        path.push_str(delimiter);
+                        rest = &rest[delimiter.len()..];
+                        continue
+                    }
+                    if matches!(c, '[' | ']' | '\\') || (c == '#' && rest.len() == key.len()){
+                        path.push('\\');
+                    }
+                    path.push(c);
+                    rest = &rest[c.len_utf8()..];
+                }
+            },
+            Segment::Slice(slice) => path.push_str(&slice.to_string()),
+            Segment::Int(_) | Segment::Raw(_) => {
+                if pos > 0{
+                    path.push_str(delimiter);
+                }
+                path.push_str(&segment.to_string())
+            }
+        }
+    }
+    path
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(path: &str) -> Vec<String> {
+        parse(path, sniff_delimiter(path)).unwrap().iter().map(Segment::to_string).collect()
+    }
+
+    fn error(path: &str) -> (ParseErrorKind, usize) {
+        let e = parse(path, sniff_delimiter(path)).unwrap_err();
+        (e.kind, e.offset)
+    }
+
+    #[test]
+    fn segments(){
+        assert_eq!(keys("terminator.1.terminator 2"), ["terminator", "1", "terminator 2"]);
+        assert_eq!(keys("a/b"), ["a", "b"]);
+        assert_eq!(keys(r"dirty\.harry.genre"), ["dirty.harry", "genre"]);
+        assert_eq!(keys(r"a\b"), [r"a\b"]);
+        assert_eq!(keys(r#"items[0]["x.y"]['z\'s'].last"#), ["items", "0", "x.y", "z's", "last"]);
+        assert_eq!(keys("items[-1:]"), ["items", "[-1:]"]);
+        assert_eq!(keys("[::2]"), ["[::2]"]);
+        assert_eq!(keys(""), [""]);
+        assert_eq!(parse("a::b", Some("::")).unwrap(), [Segment::Key("a".into()), Segment::Key("b".into())]);
+        let segments = parse(r"#42.[#-1].\#7.#x.['#3']", Some(DOT)).unwrap();
+        assert_eq!(segments, [Segment::Int(42), Segment::Int(-1), Segment::Key("#7".into()), Segment::Key("#x".into()), Segment::Key("#3".into())]);
+    }
+
+    #[test]
+    fn syntax_errors(){
+        assert_eq!(error("spaceballs."), (ParseErrorKind::EmptySegment, 11));
+        assert_eq!(error("a..b"), (ParseErrorKind::EmptySegment, 2));
+        assert_eq!(error(r"a.b\"), (ParseErrorKind::DanglingEscape, 3));
+        assert_eq!(error("a[0"), (ParseErrorKind::UnterminatedBracket, 1));
+        assert_eq!(error("a['x]"), (ParseErrorKind::UnterminatedBracket, 1));
+        assert_eq!(error("a[1:x]"), (ParseErrorKind::BadSlice, 4));
+        assert_eq!(error("a[::0]"), (ParseErrorKind::BadSlice, 4));
+        assert_eq!(error("a[0]b"), (ParseErrorKind::ExpectedDelimiter, 4));
+        assert_eq!(parse("a", Some("")).unwrap_err().kind, ParseErrorKind::InvalidDelimiter(String::new()));
+        let e = parse("ab.", Some(".")).unwrap_err();
+        assert_eq!(e.to_string(), "empty segment at offset 3\n    ab.\n       ^");
+    }
+
+    #[test]
+    fn render_round_trips(){
+        for path in [r"dirty\.harry.genre", r"a\[b\].c\\", "items[1:3].name", "x[::-1]", r"#1.\#2.c#"]{
+            let segments = parse(path, sniff_delimiter(path)).unwrap();
+            assert_eq!(render(&segments, DOT), path);
+        }
+        // keys holding the other separator are quoted
+        for (segments, delimiter, path) in [
+            (vec!["a/b"], DOT, r#"["a/b"]"#),
+            (vec!["x", r#"a/"b""#], DOT, r#"x["a/\"b\""]"#),
+            (vec!["a.b", "c"], SLASH, r#"["a.b"]/c"#),
+            (vec!["a/b", "c"], SLASH, r"a\/b/c")
+        ]{
+            let segments: Vec<Segment> = segments.into_iter().map(|key| Segment::Key(key.to_owned())).collect();
+            assert_eq!(render(&segments, delimiter), path);
+            assert_eq!(parse(path, sniff_delimiter(path)).unwrap(), segments);
+        }
+    }
+}
@@ -0,0 +1,908 @@
+//! Conversions applied to found values through the `rtype` argument.
+//!
+//! `rtype` is either the name of a conversion, a type (built-in types
+//! mapping to the same conversions as their names) or any callable taking the
+//! found value.
+//!
+//! Unless `on_error` says otherwise, values that cannot be converted are
+//! returned untouched (or replaced by the default value), exceptions raised
+//! by callables propagate, and decimals, UUIDs and IP addresses are validated
+//! instead: invalid ones are handled as missing values.
+//!
+//! `convert_json` applies the same conversions to JSON values handled in
+//! Rust (by the `dictor` binary), without going through python objects.
+
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::types::{PyBool, PyByteArray, PyBytes, PyDict, PyFloat, PyList, PyLong, PyString, PyTuple};
+use pyo3::sync::GILOnceCell;
+use pyo3::{FromPyObject, PyAny, PyErr, PyObject, PyResult, Python, ToPyObject};
+use serde_json::Value;
+
+use crate::ConversionError;
+use crate::identifiers::{to_ip, to_ip_network, to_uuid};
+use crate::json::to_py;
+use crate::temporal::{to_date, to_datetime, to_time, to_timedelta, Timezone, Unit};
+
+
+/// Types (or factories) converted in Rust when given as `rtype`, along with
+/// the name of their conversion.
+const BUILTIN_TYPES: [(&str, &str, &str); 15] = [
+    ("builtins", "str", "str"),
+    ("builtins", "int", "int"),
+    ("builtins", "float", "float"),
+    ("builtins", "bool", "bool"),
+    ("builtins", "list", "list"),
+    ("builtins", "dict", "dict"),
+    ("builtins", "bytes", "bytes"),
+    ("datetime", "datetime", "datetime"),
+    ("datetime", "date", "date"),
+    ("datetime", "time", "time"),
+    ("datetime", "timedelta", "timedelta"),
+    ("decimal", "Decimal", "decimal"),
+    ("uuid", "UUID", "uuid"),
+    ("ipaddress", "ip_address", "ip"),
+    ("ipaddress", "ip_network", "ip_network")
+];
+
+/// `BUILTIN_TYPES` along with their conversion, imported once.
+static BUILTIN_TYPE_OBJECTS: GILOnceCell<Vec<(PyObject, &str)>> = GILOnceCell::new();
+
+
+/// The `rtype` argument: a conversion name or a python object.
+#[derive(FromPyObject)]
+pub(crate) enum RType<'p>{
+    Name(String),
+    Object(&'p PyAny)
+}
+
+impl From<&str> for RType<'_>{
+    fn from(value: &str) -> Self {
+        RType::Name(value.to_owned())
+    }
+}
+
+
+enum ReturnType{
+    String,
+    Int,
+    Float,
+    Bool,
+    List,
+    Dict,
+    Bytes,
+    DateTime,
+    Date,
+    Time,
+    TimeDelta,
+    Decimal,
+    Uuid,
+    Ip,
+    IpNetwork,
+    /// Any other callable, called with the found value
+    Callable(PyObject),
+    None
+}
+
+impl ReturnType {
+    fn name(&self) -> &'static str {
+        match self {
+            ReturnType::String => "str",
+            ReturnType::Int => "int",
+            ReturnType::Float => "float",
+            ReturnType::Bool => "bool",
+            ReturnType::List => "list",
+            ReturnType::Dict => "dict",
+            ReturnType::Bytes => "bytes",
+            ReturnType::DateTime => "datetime",
+            ReturnType::Date => "date",
+            ReturnType::Time => "time",
+            ReturnType::TimeDelta => "timedelta",
+            ReturnType::Decimal => "decimal",
+            ReturnType::Uuid => "uuid",
+            ReturnType::Ip => "ip",
+            ReturnType::IpNetwork => "ip_network",
+            ReturnType::Callable(_) => "callable",
+            ReturnType::None => "None"
+        }
+    }
+
+    fn from_object(py: Python, rtype: &PyAny) -> PyResult<Self> {
+        let builtin_types = BUILTIN_TYPE_OBJECTS.get_or_try_init(py, || {
+            BUILTIN_TYPES.iter()
+                .map(|&(module, name, conversion)| Ok((py.import(module)?.getattr(name)?.to_object(py), conversion)))
+                .collect::<PyResult<Vec<_>>>()
+        })?;
+        for (builtin_type, conversion) in builtin_types{
+            if rtype.is(builtin_type){
+                return ReturnType::try_from(conversion.to_string())
+            }
+        }
+        if !rtype.is_callable(){
+            return Err(PyTypeError::new_err(format!("rtype must be a name, a type or a callable, not {}", rtype.get_type().name()?)))
+        }
+        Ok(ReturnType::Callable(rtype.to_object(py)))
+    }
+
+    /// Whether values failing the conversion are treated as missing values.
+    fn validates(&self) -> bool {
+        matches!(self, ReturnType::Decimal | ReturnType::Uuid | ReturnType::Ip | ReturnType::IpNetwork)
+    }
+}
+
+impl TryFrom<String> for ReturnType{
+    type Error = PyErr;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str(){
+            "str" => Ok(ReturnType::String),
+            "int" => Ok(ReturnType::Int),
+            "float" => Ok(ReturnType::Float),
+            "bool" => Ok(ReturnType::Bool),
+            "list" => Ok(ReturnType::List),
+            "dict" => Ok(ReturnType::Dict),
+            "bytes" => Ok(ReturnType::Bytes),
+            "datetime" => Ok(ReturnType::DateTime),
+            "date" => Ok(ReturnType::Date),
+            "time" => Ok(ReturnType::Time),
+            "timedelta" => Ok(ReturnType::TimeDelta),
+            "decimal" => Ok(ReturnType::Decimal),
+            "uuid" => Ok(ReturnType::Uuid),
+            "ip" => Ok(ReturnType::Ip),
+            "ip_network" => Ok(ReturnType::IpNetwork),
+            _ => Err(PyValueError::new_err(format!(
+                "invalid rtype: {value} (expected str, int, float, bool, list, dict, bytes, datetime, date, time, \
+                timedelta, decimal, uuid, ip or ip_network)"
+            )))
+        }
+    }
+}
+
+
+/// How `rtype="int"` handles values with a fractional part.
+#[derive(Clone, Copy)]
+enum Rounding{
+    /// Towards zero
+    Truncate,
+    /// Towards negative infinity
+    Floor,
+    /// Towards positive infinity
+    Ceil,
+    /// To the nearest integer, ties to the even one (as python's `round`)
+    HalfEven,
+    /// To the nearest integer, ties away from zero
+    HalfUp
+}
+
+impl Rounding {
+    fn round(self, value: f64) -> f64 {
+        match self {
+            Rounding::Truncate => value.trunc(),
+            Rounding::Floor => value.floor(),
+            Rounding::Ceil => value.ceil(),
+            Rounding::HalfEven => value.round_ties_even(),
+            Rounding::HalfUp => value.round()
+        }
+    }
+}
+
+impl TryFrom<&str> for Rounding{
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "truncate" => Ok(Rounding::Truncate),
+            "floor" => Ok(Rounding::Floor),
+            "ceil" => Ok(Rounding::Ceil),
+            "half_even" => Ok(Rounding::HalfEven),
+            "half_up" => Ok(Rounding::HalfUp),
+            _ => Err(format!("invalid rounding: {value} (expected truncate, floor, ceil, half_even or half_up)"))
+        }
+    }
+}
+
+
+/// What to return for values that cannot be converted.
+#[derive(Clone, Copy)]
+enum OnError{
+    Raise,
+    /// The default value, `None` without one
+    Default,
+    /// The value as found
+    Raw
+}
+
+impl TryFrom<&str> for OnError{
+    type Error = PyErr;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "raise" => Ok(OnError::Raise),
+            "default" => Ok(OnError::Default),
+            "raw" => Ok(OnError::Raw),
+            _ => Err(PyValueError::new_err(format!("invalid on_error policy: {value} (expected raise, default or raw)")))
+        }
+    }
+}
+
+
+/// The conversion requested through `rtype`, along with its options.
+pub(crate) struct Conversion{
+    return_type: ReturnType,
+    rounding: Rounding,
+    /// Explicit policy for values that cannot be converted
+    on_error: Option<OnError>,
+    /// Unit of epoch timestamps and numeric durations
+    unit: Unit,
+    timezone: Timezone
+}
+
+impl Conversion {
+    pub(crate) fn new(py: Python,
+        rtype: Option<RType>,
+        rounding: Option<&str>,
+        strict: Option<bool>,
+        unit: Option<&str>,
+        tz: Option<&str>,
+        on_error: Option<&str>
+    ) -> PyResult<Self> {
+        let return_type = match rtype {
+            Some(RType::Name(name)) => ReturnType::try_from(name)?,
+            Some(RType::Object(rtype)) => ReturnType::from_object(py, rtype)?,
+            None => ReturnType::None
+        };
+        // `strict=True` is a shorthand for `on_error="raise"`
+        let on_error = match on_error {
+            Some(on_error) => Some(on_error.try_into()?),
+            None => strict.unwrap_or(false).then_some(OnError::Raise)
+        };
+        Ok(Self {
+            return_type,
+            rounding: rounding.unwrap_or("truncate").try_into().map_err(PyValueError::new_err)?,
+            on_error,
+            unit: unit.unwrap_or("s").try_into()?,
+            timezone: Timezone::new(py, tz)?
+        })
+    }
+
+    /// Converts a found value, handling values that cannot be converted as
+    /// told by `on_error` (see the module docs when not given).
+    pub(crate) fn apply<'p>(&self, py: Python<'p>,
+        inner_object: &'p PyAny,
+        default: Option<&'p PyAny>,
+        checknone: bool
+    ) -> PyResult<Option<&'p PyAny>> {
+        let error = match cast(py, inner_object, self) {
+            Ok(Some(casted)) => return Ok(Some(casted)),
+            Ok(None) => {
+                let repr = inner_object.repr().map_or_else(|_| inner_object.to_string(), |repr| repr.to_string());
+                ConversionError::new_err(format!("cannot convert {repr} to {}", self.return_type.name()))
+            },
+            Err(e) => e
+        };
+        match self.on_error {
+            Some(OnError::Raise) => Err(error),
+            Some(OnError::Default) => Ok(default),
+            Some(OnError::Raw) => Ok(Some(inner_object)),
+            None if matches!(self.return_type, ReturnType::Callable(_)) => Err(error),
+            None if !self.return_type.validates() => Ok(Some(default.unwrap_or(inner_object))),
+            None if default.is_some() => Ok(default),
+            None if checknone => Err(error),
+            None => Ok(None)
+        }
+    }
+}
+
+
+/// Parses the usual spellings of booleans found in documents.
+fn parse_bool(content: &str) -> Option<bool> {
+    match content.trim().to_lowercase().as_str() {
+        "true" | "yes" | "on" | "1" => Some(true),
+        "false" | "no" | "off" | "0" => Some(false),
+        _ => None
+    }
+}
+
+
+/// A decimal number split around its (exponent adjusted) decimal point.
+struct Decimal{
+    negative: bool,
+    integer: String,
+    fraction: String
+}
+
+impl Decimal {
+    /// Python's limit on the digits of integers built from strings
+    const MAX_DIGITS: usize = 4300;
+
+    /// Digits of a number part, allowing single underscores between digits.
+    fn digits(part: &str) -> Option<String> {
+        if part.starts_with('_') || part.ends_with('_') || part.contains("__") ||
+            !part.chars().all(|c| c.is_ascii_digit() || c == '_'){
+            return None
+        }
+        Some(part.replace('_', ""))
+    }
+
+    /// Parses integer, decimal and scientific notations (`" -1_000.5e-2 "`).
+    fn parse(content: &str) -> Option<Self> {
+        let content = content.trim();
+        let (negative, unsigned) = match content.strip_prefix('-') {
+            Some(unsigned) => (true, unsigned),
+            None => (false, content.strip_prefix('+').unwrap_or(content))
+        };
+        let (mantissa, exponent) = match unsigned.split_once(['e', 'E']) {
+            Some((mantissa, exponent)) => {
+                let (exponent_negative, exponent_digits) = match exponent.strip_prefix('-') {
+                    Some(digits) => (true, digits),
+                    None => (false, exponent.strip_prefix('+').unwrap_or(exponent))
+                };
+                let exponent = Decimal::digits(exponent_digits).filter(|d| !d.is_empty())?.parse::<usize>().ok()
+                    .filter(|e| *e <= Decimal::MAX_DIGITS)? as isize;
+                (mantissa, if exponent_negative { -exponent } else { exponent })
+            },
+            None => (unsigned, 0)
+        };
+        let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        let (integer, fraction) = (Decimal::digits(integer)?, Decimal::digits(fraction)?);
+        if integer.is_empty() && fraction.is_empty(){
+            return None
+        }
+
+        let digits = integer.clone() + &fraction;
+        let point = integer.len() as isize + exponent;
+        let (integer, fraction) = if point <= 0{
+            (String::new(), "0".repeat(point.unsigned_abs()) + &digits)
+        }else if point as usize >= digits.len(){
+            (digits.clone() + &"0".repeat(point as usize - digits.len()), String::new())
+        }else{
+            let (integer, fraction) = digits.split_at(point as usize);
+            (integer.to_owned(), fraction.to_owned())
+        };
+        Some(Self { negative, integer, fraction })
+    }
+
+    /// Whether rounding to an integer moves away from zero.
+    fn rounds_up(&self, rounding: Rounding) -> bool {
+        let fraction = self.fraction.trim_end_matches('0');
+        if fraction.is_empty(){
+            return false
+        }
+        match rounding {
+            Rounding::Truncate => false,
+            Rounding::Floor => self.negative,
+            Rounding::Ceil => !self.negative,
+            Rounding::HalfUp => fraction >= "5",
+            Rounding::HalfEven => match fraction {
+                "5" => self.integer.ends_with(['1', '3', '5', '7', '9']),
+                _ => fraction > "5"
+            }
+        }
+    }
+
+    /// Digits (signed) of the integer the number rounds to, `None` past
+    /// python's limit.
+    fn integer(&self, rounding: Rounding) -> Option<String> {
+        let mut digits = self.integer.trim_start_matches('0').to_owned();
+        if self.rounds_up(rounding){
+            digits = increment(&digits);
+        }
+        Some(match digits.as_str() {
+            _ if digits.len() > Decimal::MAX_DIGITS => return None,
+            "" => "0".to_owned(),
+            _ if self.negative => format!("-{digits}"),
+            _ => digits
+        })
+    }
+}
+
+
+/// The natural number written `digits` plus one.
+fn increment(digits: &str) -> String {
+    let mut digits = digits.as_bytes().to_vec();
+    for digit in digits.iter_mut().rev(){
+        if *digit < b'9'{
+            *digit += 1;
+            return String::from_utf8(digits).unwrap_or_default()
+        }
+        *digit = b'0';
+    }
+    digits.insert(0, b'1');
+    String::from_utf8(digits).unwrap_or_default()
+}
+
+
+/// The python `int` written `digits`.
+fn py_int<'p>(py: Python<'p>, digits: &str) -> Option<&'p PyAny> {
+    match digits.parse::<i128>() {
+        Ok(integer) => Some(integer.to_object(py).into_ref(py)),
+        Err(_) => py.get_type::<PyLong>().call1((digits,)).ok()
+    }
+}
+
+
+fn to_int<'p>(py: Python<'p>, inner_object: &'p PyAny, rounding: Rounding) -> Option<&'p PyAny> {
+    if let Ok(flag) = inner_object.downcast::<PyBool>(){
+        Some(i32::from(flag.is_true()).to_object(py).into_ref(py))
+    }else if inner_object.is_instance_of::<PyLong>(){
+        Some(inner_object)
+    }else if let Ok(content) = inner_object.downcast::<PyFloat>(){
+        let content = content.value();
+        if !content.is_finite(){
+            return None
+        }
+        // floats hold integers beyond i128 as well, which python converts exactly
+        py.get_type::<PyLong>().call1((rounding.round(content),)).ok()
+    }else if let Ok(content) = inner_object.downcast::<PyString>(){
+        py_int(py, &Decimal::parse(content.to_str().ok()?)?.integer(rounding)?)
+    }else{
+        // `decimal.Decimal`s, exactly, through their string form
+        let decimal = py.import("decimal").ok()?.getattr("Decimal").ok()?;
+        inner_object.is_instance(decimal).ok()?.then_some(())?;
+        py_int(py, &Decimal::parse(inner_object.str().ok()?.to_str().ok()?)?.integer(rounding)?)
+    }
+}
+
+
+/// Builds `decimal.Decimal`s from integers, floats (by their shortest
+/// representation, so `0.1` stays `Decimal("0.1")`) and decimal strings.
+fn to_decimal<'p>(py: Python<'p>, inner_object: &'p PyAny) -> Option<&'p PyAny> {
+    let decimal = py.import("decimal").ok()?.getattr("Decimal").ok()?;
+    if inner_object.is_instance(decimal).ok()?{
+        return Some(inner_object)
+    }
+    let content = if let Ok(content) = inner_object.downcast::<PyString>(){
+        let content = content.to_str().ok()?.trim();
+        Decimal::parse(content)?;
+        content.to_owned()
+    }else if inner_object.is_instance_of::<PyBool>(){
+        return None
+    }else if inner_object.is_instance_of::<PyLong>(){
+        inner_object.str().ok()?.to_string()
+    }else if let Ok(content) = inner_object.downcast::<PyFloat>(){
+        content.value().is_finite().then_some(())?;
+        content.repr().ok()?.to_string()
+    }else{
+        return None
+    };
+    decimal.call1((content,)).ok()
+}
+
+
+fn to_float<'p>(py: Python<'p>, inner_object: &'p PyAny) -> Option<&'p PyAny> {
+    if inner_object.is_instance_of::<PyFloat>(){
+        return Some(inner_object)
+    }
+    let content = match inner_object.downcast::<PyString>() {
+        Ok(content) => content.to_str().ok()?.trim().parse::<f64>().ok()?,
+        Err(_) => inner_object.extract::<f64>().ok()?
+    };
+    Some(PyFloat::new(py, content))
+}
+
+
+fn to_bool<'p>(py: Python<'p>, inner_object: &'p PyAny) -> Option<&'p PyAny> {
+    if inner_object.is_instance_of::<PyBool>(){
+        return Some(inner_object)
+    }
+    let content = match inner_object.downcast::<PyString>() {
+        Ok(content) => parse_bool(content.to_str().ok()?)?,
+        Err(_) => inner_object.extract::<f64>().ok()? != 0.0
+    };
+    Some(PyBool::new(py, content))
+}
+
+
+fn to_list<'p>(py: Python<'p>, inner_object: &'p PyAny) -> Option<&'p PyAny> {
+    if inner_object.is_instance_of::<PyList>(){
+        Some(inner_object)
+    }else if let Ok(tuple) = inner_object.downcast::<PyTuple>(){
+        Some(tuple.to_list())
+    }else if inner_object.is_none(){
+        Some(PyList::empty(py))
+    }else{
+        Some(PyList::new(py, [inner_object]))
+    }
+}
+
+
+fn to_dict<'p>(py: Python<'p>, inner_object: &'p PyAny) -> Option<&'p PyAny> {
+    if inner_object.is_instance_of::<PyDict>(){
+        return Some(inner_object)
+    }
+    if let Ok(content) = inner_object.downcast::<PyString>(){
+        // JSON objects embedded as text
+        return match serde_json::from_str::<Value>(content.to_str().ok()?).ok()? {
+            value @ Value::Object(_) => Some(to_py(py, &value).into_ref(py)),
+            _ => None
+        }
+    }
+    // mappings and sequences of key/value pairs
+    let dict = py.get_type::<PyDict>().call1((inner_object,)).ok()?;
+    Some(dict)
+}
+
+
+fn to_bytes<'p>(py: Python<'p>, inner_object: &'p PyAny) -> Option<&'p PyAny> {
+    if inner_object.is_instance_of::<PyBytes>(){
+        Some(inner_object)
+    }else if let Ok(content) = inner_object.downcast::<PyString>(){
+        Some(PyBytes::new(py, content.to_str().ok()?.as_bytes()))
+    }else if let Ok(content) = inner_object.downcast::<PyByteArray>(){
+        Some(PyBytes::new(py, &content.to_vec()))
+    }else{
+        None
+    }
+}
+
+
+/// A JSON value converted by [`convert_json`].
+#[derive(Debug, PartialEq)]
+pub enum Converted{
+    Value(Value),
+    /// An integer out of the range of JSON numbers (as loaded), as its digits
+    BigInt(String)
+}
+
+
+/// `repr()` of the python float `value`: its shortest representation, in
+/// scientific notation below 1e-4 and from 1e16 on.
+fn float_repr(value: f64) -> String {
+    if !value.is_finite(){
+        return match value {
+            _ if value.is_nan() => "nan".into(),
+            _ if value > 0.0 => "inf".into(),
+            _ => "-inf".into()
+        }
+    }
+    let scientific = format!("{value:e}");
+    let (mantissa, exponent) = scientific.split_once('e').unwrap_or((&scientific, "0"));
+    let exponent = exponent.parse::<i32>().unwrap_or_default();
+    let (sign, mantissa) = match mantissa.strip_prefix('-') {
+        Some(mantissa) => ("-", mantissa),
+        None => ("", mantissa)
+    };
+    if !(-4..16).contains(&exponent){
+        let exponent_sign = if exponent < 0 { "-" } else { "+" };
+        return format!("{sign}{mantissa}e{exponent_sign}{:02}", exponent.abs())
+    }
+    let digits = mantissa.replace('.', "");
+    let point = exponent + 1;
+    if point <= 0{
+        format!("{sign}0.{}{digits}", "0".repeat(point.unsigned_abs() as usize))
+    }else if point as usize >= digits.len(){
+        format!("{sign}{digits}{}.0", "0".repeat(point as usize - digits.len()))
+    }else{
+        let (integer, fraction) = digits.split_at(point as usize);
+        format!("{sign}{integer}.{fraction}")
+    }
+}
+
+
+/// `repr()` of the python object `json.loads` builds for `value`.
+fn py_repr(value: &Value) -> String {
+    match value {
+        Value::Null => "None".into(),
+        Value::Bool(flag) => if *flag { "True".into() } else { "False".into() },
+        Value::Number(number) if number.is_f64() => float_repr(number.as_f64().unwrap_or(f64::NAN)),
+        Value::Number(number) => number.to_string(),
+        Value::String(content) => {
+            let quote = if content.contains('\'') && !content.contains('"') { '"' } else { '\'' };
+            let mut repr = String::from(quote);
+            for c in content.chars(){
+                match c {
+                    '\\' => repr.push_str("\\\\"),
+                    '\n' => repr.push_str("\\n"),
+                    '\r' => repr.push_str("\\r"),
+                    '\t' => repr.push_str("\\t"),
+                    _ if c == quote => repr.extend(['\\', c]),
+                    _ if c.is_control() && (c as u32) < 0x100 => repr.push_str(&format!("\\x{:02x}", c as u32)),
+                    _ if c.is_control() => repr.push_str(&format!("\\u{:04x}", c as u32)),
+                    _ => repr.push(c)
+                }
+            }
+            repr.push(quote);
+            repr
+        },
+        Value::Array(list) => format!("[{}]", list.iter().map(py_repr).collect::<Vec<_>>().join(", ")),
+        Value::Object(map) => {
+            let items: Vec<String> = map.iter()
+                .map(|(key, item)| format!("{}: {}", py_repr(&Value::String(key.to_owned())), py_repr(item)))
+                .collect();
+            format!("{{{}}}", items.join(", "))
+        }
+    }
+}
+
+
+/// Digits (signed) of the integer `rtype="int"` converts `value` to.
+fn json_int(value: &Value, rounding: Rounding) -> Option<String> {
+    match value {
+        Value::Bool(flag) => Some(u8::from(*flag).to_string()),
+        Value::Number(number) if number.is_f64() => {
+            let content = number.as_f64().filter(|content| content.is_finite())?;
+            // adding zero turns -0.0 into 0.0
+            Some(format!("{:.0}", rounding.round(content) + 0.0))
+        },
+        Value::Number(number) => Some(number.to_string()),
+        Value::String(content) => Decimal::parse(content)?.integer(rounding),
+        _ => None
+    }
+}
+
+
+/// Key/value pairs `dict()` builds a dict from, keys written as `json.dumps`
+/// writes them.
+fn json_pairs(pairs: &[Value]) -> Option<Value> {
+    let mut dict = serde_json::Map::new();
+    for pair in pairs{
+        let (key, item) = match pair {
+            Value::Array(pair) => match pair.as_slice() {
+                [key, item] => (key, item.clone()),
+                _ => return None
+            },
+            Value::String(pair) => match pair.chars().collect::<Vec<_>>().as_slice() {
+                [key, item] => {
+                    dict.insert(key.to_string(), Value::String(item.to_string()));
+                    continue
+                },
+                _ => return None
+            },
+            _ => return None
+        };
+        let key = match key {
+            Value::String(key) => key.to_owned(),
+            Value::Null => "null".into(),
+            Value::Bool(flag) => flag.to_string(),
+            Value::Number(_) => py_repr(key),
+            _ => return None
+        };
+        dict.insert(key, item);
+    }
+    Some(Value::Object(dict))
+}
+
+
+/// Converts `value` as `rtype` (`str`, `int`, `float`, `bool`, `list` or
+/// `dict`, integers rounded as `rounding` tells) converts the python object
+/// `json.loads` builds for it, `None` meaning it cannot be converted (floats
+/// JSON cannot hold included).
+pub fn convert_json(value: &Value, rtype: &str, rounding: Option<&str>) -> Result<Option<Converted>, String> {
+    let rounding = Rounding::try_from(rounding.unwrap_or("truncate"))?;
+    let float = |content: f64| serde_json::Number::from_f64(content).map(Value::Number);
+    let converted = match (rtype, value) {
+        ("int", value) => {
+            return Ok(json_int(value, rounding).map(|digits| match digits.parse::<i64>() {
+                Ok(int) => Converted::Value(int.into()),
+                Err(_) => match digits.parse::<u64>() {
+                    Ok(int) => Converted::Value(int.into()),
+                    Err(_) => Converted::BigInt(digits)
+                }
+            }))
+        },
+        ("str", Value::String(_)) => Some(value.clone()),
+        ("str", value) => Some(Value::String(py_repr(value))),
+        ("float", Value::Number(number)) => number.as_f64().and_then(float),
+        ("float", Value::String(content)) => content.trim().parse::<f64>().ok().and_then(float),
+        ("float", Value::Bool(flag)) => float(f64::from(u8::from(*flag))),
+        ("float", _) => None,
+        ("bool", Value::Bool(_)) => Some(value.clone()),
+        ("bool", Value::String(content)) => parse_bool(content).map(Value::Bool),
+        ("bool", Value::Number(number)) => number.as_f64().map(|number| Value::Bool(number != 0.0)),
+        ("bool", _) => None,
+        ("list", Value::Array(_)) => Some(value.clone()),
+        ("list", Value::Null) => Some(Value::Array(vec![])),
+        ("list", value) => Some(Value::Array(vec![value.clone()])),
+        ("dict", Value::Object(_)) => Some(value.clone()),
+        // JSON objects embedded as text
+        ("dict", Value::String(content)) => serde_json::from_str(content).ok().filter(Value::is_object),
+        ("dict", Value::Array(pairs)) => json_pairs(pairs),
+        ("dict", _) => None,
+        _ => return Err(format!("invalid rtype: {rtype} (expected str, int, float, bool, list or dict)"))
+    };
+    Ok(converted.map(Converted::Value))
+}
+
+
+/// Converts `inner_object` to the requested return type, `None` meaning it
+/// cannot be converted. Errors are the ones raised by callables.
+fn cast<'p>(py: Python<'p>, inner_object: &'p PyAny, conversion: &Conversion) -> PyResult<Option<&'p PyAny>> {
+    let converted = match &conversion.return_type{
+        ReturnType::String => inner_object.str().ok().map(|content| content.into()),
+        ReturnType::Int => to_int(py, inner_object, conversion.rounding),
+        ReturnType::Float => to_float(py, inner_object),
+        ReturnType::Bool => to_bool(py, inner_object),
+        ReturnType::List => to_list(py, inner_object),
+        ReturnType::Dict => to_dict(py, inner_object),
+        ReturnType::Bytes => to_bytes(py, inner_object),
+        ReturnType::DateTime => to_datetime(py, inner_object, conversion.unit, &conversion.timezone),
+        ReturnType::Date => to_date(py, inner_object, conversion.unit, &conversion.timezone),
+        ReturnType::Time => to_time(py, inner_object, conversion.unit, &conversion.timezone),
+        ReturnType::TimeDelta => to_timedelta(py, inner_object, conversion.unit),
+        ReturnType::Decimal => to_decimal(py, inner_object),
+        ReturnType::Uuid => to_uuid(py, inner_object),
+        ReturnType::Ip => to_ip(py, inner_object),
+        ReturnType::IpNetwork => to_ip_network(py, inner_object),
+        ReturnType::Callable(callable) => return callable.clone_ref(py).into_ref(py).call1((inner_object,)).map(Some),
+        ReturnType::None => Some(inner_object) // keep original value
+    };
+    Ok(converted)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cast_eval(py: Python, value: &str, rtype: &str) -> String {
+        let value = py.eval(value, None, None).unwrap();
+        let conversion = Conversion::new(py, Some(rtype.into()), None, None, None, None, None).unwrap();
+        conversion.apply(py, value, None, false).unwrap().unwrap().repr().unwrap().to_string()
+    }
+
+    fn int_eval(py: Python, value: &str, rounding: &str) -> String {
+        let value = py.eval(value, None, None).unwrap();
+        let conversion = Conversion::new(py, Some("int".into()), Some(rounding), Some(true), None, None, None).unwrap();
+        conversion.apply(py, value, None, false).unwrap().unwrap().repr().unwrap().to_string()
+    }
+
+    #[test]
+    fn int_conversions(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            assert_eq!(int_eval(py, "' -5 '", "truncate"), "-5");
+            assert_eq!(int_eval(py, "'1_000_000'", "truncate"), "1000000");
+            assert_eq!(int_eval(py, "'123456789012345678901234567890123456789012'", "truncate"),
+                "123456789012345678901234567890123456789012");
+            assert_eq!(int_eval(py, "'-2.5'", "truncate"), "-2");
+            assert_eq!(int_eval(py, "'-2.5'", "floor"), "-3");
+            assert_eq!(int_eval(py, "'2.1'", "ceil"), "3");
+            assert_eq!(int_eval(py, "'2.5'", "half_even"), "2");
+            assert_eq!(int_eval(py, "'3.5'", "half_even"), "4");
+            assert_eq!(int_eval(py, "'2.5'", "half_up"), "3");
+            assert_eq!(int_eval(py, "'1.5e3'", "truncate"), "1500");
+            assert_eq!(int_eval(py, "'25e-1'", "half_up"), "3");
+            assert_eq!(int_eval(py, "-2.7", "truncate"), "-2");
+            assert_eq!(int_eval(py, "True", "truncate"), "1");
+            let decimal = |value: &str| format!("__import__('decimal').Decimal('{value}')");
+            assert_eq!(int_eval(py, &decimal("-2.5"), "floor"), "-3");
+            assert_eq!(int_eval(py, &decimal("2.5"), "half_even"), "2");
+            assert_eq!(int_eval(py, &decimal("12345678901234567890.99"), "ceil"), "12345678901234567891");
+            assert_eq!(int_eval(py, &decimal("1E+3"), "truncate"), "1000");
+            assert_eq!(cast_eval(py, &decimal("NaN"), "int"), "Decimal('NaN')");
+        });
+    }
+
+    #[test]
+    fn strict_conversions(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let value = py.eval("'1__0'", None, None).unwrap();
+            let strict = Conversion::new(py, Some("int".into()), None, Some(true), None, None, None).unwrap();
+            assert!(strict.apply(py, value, None, false).is_err());
+            let lenient = Conversion::new(py, Some("int".into()), None, None, None, None, None).unwrap();
+            assert_eq!(lenient.apply(py, value, None, false).unwrap().unwrap().to_string(), "1__0");
+            let default = py.eval("0", None, None).unwrap();
+            assert_eq!(lenient.apply(py, value, Some(default), false).unwrap().unwrap().to_string(), "0");
+            assert!(Conversion::new(py, Some("int".into()), Some("up"), None, None, None, None).is_err());
+        });
+    }
+
+    #[test]
+    fn scalar_conversions(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            assert_eq!(cast_eval(py, "' 2.5 '", "float"), "2.5");
+            assert_eq!(cast_eval(py, "3", "float"), "3.0");
+            assert_eq!(cast_eval(py, "'Yes'", "bool"), "True");
+            assert_eq!(cast_eval(py, "'0'", "bool"), "False");
+            assert_eq!(cast_eval(py, "0", "bool"), "False");
+            assert_eq!(cast_eval(py, "'maybe'", "bool"), "'maybe'");
+            assert_eq!(cast_eval(py, "'abc'", "bytes"), "b'abc'");
+            assert_eq!(cast_eval(py, "'comedy'", "float"), "'comedy'");
+        });
+    }
+
+    #[test]
+    fn container_conversions(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            assert_eq!(cast_eval(py, "'a'", "list"), "['a']");
+            assert_eq!(cast_eval(py, "(1, 2)", "list"), "[1, 2]");
+            assert_eq!(cast_eval(py, "None", "list"), "[]");
+            assert_eq!(cast_eval(py, "[1]", "list"), "[1]");
+            assert_eq!(cast_eval(py, "'{\"a\": 1}'", "dict"), "{'a': 1}");
+            assert_eq!(cast_eval(py, "[('a', 1)]", "dict"), "{'a': 1}");
+            assert_eq!(cast_eval(py, "'[1]'", "dict"), "'[1]'");
+        });
+    }
+
+    #[test]
+    fn validated_conversions(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            assert_eq!(cast_eval(py, "' 10.10 '", "decimal"), "Decimal('10.10')");
+            assert_eq!(cast_eval(py, "0.1", "decimal"), "Decimal('0.1')");
+            assert_eq!(cast_eval(py, "'12345678-1234-5678-1234-567812345678'", "uuid"),
+                "UUID('12345678-1234-5678-1234-567812345678')");
+            assert_eq!(cast_eval(py, "'::1'", "ip"), "IPv6Address('::1')");
+            assert_eq!(cast_eval(py, "'10.0.0.0/8'", "ip_network"), "IPv4Network('10.0.0.0/8')");
+
+            // invalid values are handled as missing ones
+            let value = py.eval("'12.5 EUR'", None, None).unwrap();
+            let conversion = Conversion::new(py, Some("decimal".into()), None, None, None, None, None).unwrap();
+            assert!(conversion.apply(py, value, None, false).unwrap().is_none());
+            assert!(conversion.apply(py, value, None, true).is_err());
+            let default = py.eval("0", None, None).unwrap();
+            assert!(conversion.apply(py, value, Some(default), true).unwrap().unwrap().is(default));
+        });
+    }
+
+    #[test]
+    fn type_and_callable_rtypes(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let apply = |rtype: &str, value: &str, on_error: Option<&str>| {
+                let rtype = py.eval(rtype, None, None).unwrap();
+                let conversion = Conversion::new(py, Some(RType::Object(rtype)), None, None, None, None, on_error)?;
+                let default = py.eval("-1", None, None).unwrap();
+                conversion.apply(py, py.eval(value, None, None).unwrap(), Some(default), false)
+                    .map(|converted| converted.map(|converted| converted.repr().unwrap().to_string()))
+            };
+            // built-in types use the conversions of their names
+            assert_eq!(apply("int", "' 1_000 '", None).unwrap().unwrap(), "1000");
+            assert_eq!(apply("__import__('decimal').Decimal", "'1.10'", None).unwrap().unwrap(), "Decimal('1.10')");
+            assert_eq!(apply("lambda v: v * 2", "21", None).unwrap().unwrap(), "42");
+
+            assert!(apply("lambda v: 1 / 0", "21", None).is_err());
+            assert_eq!(apply("lambda v: 1 / 0", "21", Some("default")).unwrap().unwrap(), "-1");
+            assert_eq!(apply("lambda v: 1 / 0", "21", Some("raw")).unwrap().unwrap(), "21");
+            assert!(apply("float", "'abc'", Some("raise")).is_err());
+            assert!(apply("42", "'abc'", None).is_err());
+        });
+    }
+
+    #[test]
+    fn json_conversions_match_python(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let values = [
+                "null", "true", "0", "-7", "18446744073709551615", "2.5", "-2.5", "1e300", "0.1", "1e-7", "123456789.0",
+                r#"" 1_983 ""#, r#""2.5""#, r#""-0.4""#, r#""123456789012345678901234567890.5""#, r#""1e3""#, r#""Yes""#,
+                r#""it's""#, r#""x\n\"y'""#, r#""{\"a\": 1}""#, r#"[1, "a", null]"#, r#"[["a", 1], ["b", true], "cd"]"#,
+                r#"{"a": [1.5, {"b": false}]}"#
+            ];
+            for rtype in ["str", "int", "float", "bool", "list", "dict"]{
+                for rounding in ["truncate", "floor", "half_even", "half_up"]{
+                    let conversion = Conversion::new(py, Some(rtype.into()), Some(rounding), None, None, None, None).unwrap();
+                    for value in values{
+                        let value: Value = serde_json::from_str(value).unwrap();
+                        let object = to_py(py, &value).into_ref(py);
+                        let expected = cast(py, object, &conversion).unwrap().filter(|converted| {
+                            // floats JSON cannot hold are not converted
+                            converted.extract::<f64>().map_or(true, f64::is_finite) || converted.is_instance_of::<PyLong>()
+                        });
+                        let converted = match convert_json(&value, rtype, Some(rounding)).unwrap() {
+                            Some(Converted::Value(converted)) => Some(to_py(py, &converted).into_ref(py)),
+                            Some(Converted::BigInt(digits)) => py_int(py, &digits),
+                            None => None
+                        };
+                        let repr = |converted: Option<&PyAny>| converted.map(|converted| converted.repr().unwrap().to_string());
+                        assert_eq!(repr(converted), repr(expected), "{value} to {rtype} ({rounding})");
+                    }
+                }
+            }
+            assert!(convert_json(&Value::Null, "bytes", None).is_err());
+            assert!(convert_json(&Value::Null, "int", Some("up")).is_err());
+        });
+    }
+
+    #[test]
+    fn unknown_rtype(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let err = Conversion::new(py, Some("integer".into()), None, None, None, None, None).err().unwrap();
+            assert!(err.is_instance_of::<PyValueError>(py));
+            assert!(Conversion::new(py, None, None, None, None, None, None).is_ok());
+        });
+    }
+}
@@ -0,0 +1,405 @@
+//! Validation of documents against a JSON Schema (draft 2020-12) subset.
+//!
+//! Supported keywords are `type`, `enum`, `const`, `required`,
+//! `properties`, `additionalProperties`, `items`, `minimum`, `maximum`,
+//! `exclusiveMinimum`, `exclusiveMaximum`, `minLength`, `maxLength`,
+//! `pattern`, `minItems` and `maxItems`; any other keyword is ignored. The
+//! document is walked as the python objects `dictor` walks, and errors are
+//! located by the path `dictor` would address them with.
+
+use pyo3::exceptions::PyValueError;
+use pyo3::types::{PyBool, PyDict, PyFloat, PyList, PyLong, PyString};
+use pyo3::{pyfunction, PyAny, PyObject, PyResult, Python, ToPyObject};
+use regex::Regex;
+
+use crate::path::{self, Segment};
+use crate::traverse::{is_mapping, is_sequence, mapping_items};
+use crate::key_segment;
+
+
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum JsonType{
+    Null,
+    Boolean,
+    Integer,
+    Number,
+    String,
+    Array,
+    Object
+}
+
+impl JsonType {
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            JsonType::Null => "null",
+            JsonType::Boolean => "boolean",
+            JsonType::Integer => "integer",
+            JsonType::Number => "number",
+            JsonType::String => "string",
+            JsonType::Array => "array",
+            JsonType::Object => "object"
+        }
+    }
+
+    /// The narrowest type of `instance`, `None` for values JSON has no type
+    /// for.
+    pub(crate) fn of(instance: &PyAny) -> Option<Self> {
+        [JsonType::Null, JsonType::Boolean, JsonType::Number, JsonType::String, JsonType::Array, JsonType::Object]
+            .into_iter()
+            .find(|json_type| json_type.matches(instance))
+            .map(|json_type| match json_type {
+                JsonType::Number if instance.is_instance_of::<PyLong>() => JsonType::Integer,
+                json_type => json_type
+            })
+    }
+
+    /// Whether `instance` is of this type. Booleans are not numbers, while
+    /// floats without a fractional part are integers.
+    fn matches(&self, instance: &PyAny) -> bool {
+        let is_number = (instance.is_instance_of::<PyLong>() || instance.is_instance_of::<PyFloat>()) && !instance.is_instance_of::<PyBool>();
+        match self {
+            JsonType::Null => instance.is_none(),
+            JsonType::Boolean => instance.is_instance_of::<PyBool>(),
+            JsonType::Integer => is_number && (instance.is_instance_of::<PyLong>() ||
+                instance.extract::<f64>().is_ok_and(|number| number.fract() == 0.0)),
+            JsonType::Number => is_number,
+            JsonType::String => instance.is_instance_of::<PyString>(),
+            JsonType::Array => is_sequence(instance),
+            JsonType::Object => is_mapping(instance)
+        }
+    }
+}
+
+impl TryFrom<&str> for JsonType{
+    type Error = pyo3::PyErr;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "null" => Ok(JsonType::Null),
+            "boolean" => Ok(JsonType::Boolean),
+            "integer" => Ok(JsonType::Integer),
+            "number" => Ok(JsonType::Number),
+            "string" => Ok(JsonType::String),
+            "array" => Ok(JsonType::Array),
+            "object" => Ok(JsonType::Object),
+            _ => Err(PyValueError::new_err(format!("invalid schema type: {value}")))
+        }
+    }
+}
+
+
+/// A schema compiled from its python representation.
+enum Schema{
+    /// `true` accepts anything and `false` nothing
+    Bool(bool),
+    Rules(Box<Rules>)
+}
+
+struct Rules{
+    types: Option<Vec<JsonType>>,
+    enum_values: Option<Vec<PyObject>>,
+    constant: Option<PyObject>,
+    required: Vec<String>,
+    properties: Vec<(String, Schema)>,
+    additional_properties: Option<Schema>,
+    items: Option<Schema>,
+    minimum: Option<f64>,
+    maximum: Option<f64>,
+    exclusive_minimum: Option<f64>,
+    exclusive_maximum: Option<f64>,
+    min_length: Option<usize>,
+    max_length: Option<usize>,
+    pattern: Option<Regex>,
+    min_items: Option<usize>,
+    max_items: Option<usize>
+}
+
+
+impl Schema {
+    fn compile(py: Python, schema: &PyAny) -> PyResult<Self> {
+        if let Ok(accepts) = schema.downcast::<PyBool>(){
+            return Ok(Schema::Bool(accepts.is_true()))
+        }
+        let schema = schema.downcast::<PyDict>()
+            .map_err(|_| PyValueError::new_err(format!("invalid schema: {}", schema.repr().map(ToString::to_string).unwrap_or_default())))?;
+        let keyword = |name: &str| schema.get_item(name);
+        let number = |name: &str| keyword(name).map(|value| value.extract::<f64>()).transpose();
+        let count = |name: &str| keyword(name).map(|value| value.extract::<usize>()).transpose();
+
+        let types = match keyword("type") {
+            Some(name) if name.is_instance_of::<PyString>() => Some(vec![name.extract::<&str>()?.try_into()?]),
+            Some(names) => Some(names.extract::<Vec<&str>>()?.into_iter().map(JsonType::try_from).collect::<PyResult<_>>()?),
+            None => None
+        };
+        let pattern = match keyword("pattern") {
+            Some(pattern) => Some(Regex::new(pattern.extract()?)
+                .map_err(|e| PyValueError::new_err(format!("invalid schema pattern: {e}")))?),
+            None => None
+        };
+        let properties = match keyword("properties") {
+            Some(properties) => properties.downcast::<PyDict>()?.iter()
+                .map(|(name, schema)| Ok((name.extract()?, Schema::compile(py, schema)?)))
+                .collect::<PyResult<_>>()?,
+            None => vec![]
+        };
+        Ok(Schema::Rules(Box::new(Rules {
+            types,
+            enum_values: keyword("enum").map(|values| values.extract()).transpose()?,
+            constant: keyword("const").map(|value| value.to_object(py)),
+            required: keyword("required").map(|required| required.extract()).transpose()?.unwrap_or_default(),
+            properties,
+            additional_properties: keyword("additionalProperties").map(|schema| Schema::compile(py, schema)).transpose()?,
+            items: keyword("items").map(|schema| Schema::compile(py, schema)).transpose()?,
+            minimum: number("minimum")?,
+            maximum: number("maximum")?,
+            exclusive_minimum: number("exclusiveMinimum")?,
+            exclusive_maximum: number("exclusiveMaximum")?,
+            min_length: count("minLength")?,
+            max_length: count("maxLength")?,
+            pattern,
+            min_items: count("minItems")?,
+            max_items: count("maxItems")?
+        })))
+    }
+}
+
+
+struct ValidationError{
+    path: String,
+    keyword: &'static str,
+    message: String
+}
+
+
+/// Equality as JSON sees it: booleans are never equal to numbers.
+fn json_eq(a: &PyAny, b: &PyAny) -> bool {
+    a.is_instance_of::<PyBool>() == b.is_instance_of::<PyBool>() && a.eq(b).unwrap_or(false)
+}
+
+
+fn repr(value: &PyAny) -> String {
+    value.repr().map(ToString::to_string).unwrap_or_default()
+}
+
+
+struct Validator<'p>{
+    py: Python<'p>,
+    /// Segments leading to the value being validated
+    path: Vec<Segment>,
+    errors: Vec<ValidationError>
+}
+
+impl<'p> Validator<'p> {
+    fn error(&mut self, keyword: &'static str, message: String) {
+        self.errors.push(ValidationError { path: path::render(&self.path, crate::DOT), keyword, message });
+    }
+
+    /// Validates the value found at `segment` within the current one.
+    fn validate_child(&mut self, schema: &Schema, instance: &'p PyAny, segment: Segment) {
+        self.path.push(segment);
+        self.validate(schema, instance);
+        self.path.pop();
+    }
+
+    fn validate(&mut self, schema: &Schema, instance: &'p PyAny) {
+        let rules = match schema {
+            Schema::Bool(true) => return,
+            Schema::Bool(false) => return self.error("false", "no value is allowed here".to_owned()),
+            Schema::Rules(rules) => rules
+        };
+        if let Some(types) = rules.types.as_ref().filter(|types| !types.iter().any(|t| t.matches(instance))){
+            let expected: Vec<&str> = types.iter().map(JsonType::name).collect();
+            let found = instance.get_type().name().unwrap_or("?");
+            self.error("type", format!("expected {}, got {found}", expected.join(" or ")));
+        }
+        if let Some(values) = rules.enum_values.as_ref().filter(|values| !values.iter().any(|value| json_eq(instance, value.as_ref(self.py)))){
+            let values: Vec<String> = values.iter().map(|value| repr(value.as_ref(self.py))).collect();
+            self.error("enum", format!("{} is not one of [{}]", repr(instance), values.join(", ")));
+        }
+        if let Some(constant) = rules.constant.as_ref().filter(|constant| !json_eq(instance, constant.as_ref(self.py))){
+            self.error("const", format!("{} is not {}", repr(instance), repr(constant.as_ref(self.py))));
+        }
+
+        if JsonType::Number.matches(instance){
+            self.validate_number(rules, instance);
+        }else if let Ok(text) = instance.downcast::<PyString>(){
+            self.validate_string(rules, text.to_str().unwrap_or_default());
+        }else if JsonType::Array.matches(instance){
+            self.validate_array(rules, instance);
+        }else if JsonType::Object.matches(instance){
+            self.validate_object(rules, instance);
+        }
+    }
+
+    fn validate_number(&mut self, rules: &Rules, instance: &PyAny) {
+        let Ok(number) = instance.extract::<f64>() else { return };
+        let shown = repr(instance);
+        if let Some(minimum) = rules.minimum.filter(|minimum| number < *minimum){
+            self.error("minimum", format!("{shown} is less than the minimum of {minimum}"));
+        }
+        if let Some(maximum) = rules.maximum.filter(|maximum| number > *maximum){
+            self.error("maximum", format!("{shown} is greater than the maximum of {maximum}"));
+        }
+        if let Some(minimum) = rules.exclusive_minimum.filter(|minimum| number <= *minimum){
+            self.error("exclusiveMinimum", format!("{shown} is not greater than {minimum}"));
+        }
+        if let Some(maximum) = rules.exclusive_maximum.filter(|maximum| number >= *maximum){
+            self.error("exclusiveMaximum", format!("{shown} is not less than {maximum}"));
+        }
+    }
+
+    fn validate_string(&mut self, rules: &Rules, text: &str) {
+        let length = text.chars().count();
+        if let Some(min_length) = rules.min_length.filter(|min_length| length < *min_length){
+            self.error("minLength", format!("{text:?} is shorter than {min_length} characters"));
+        }
+        if let Some(max_length) = rules.max_length.filter(|max_length| length > *max_length){
+            self.error("maxLength", format!("{text:?} is longer than {max_length} characters"));
+        }
+        if let Some(pattern) = rules.pattern.as_ref().filter(|pattern| !pattern.is_match(text)){
+            self.error("pattern", format!("{text:?} does not match {:?}", pattern.as_str()));
+        }
+    }
+
+    fn validate_array(&mut self, rules: &Rules, instance: &'p PyAny) {
+        let items: Vec<&PyAny> = instance.iter().into_iter().flatten().flatten().collect();
+        if let Some(min_items) = rules.min_items.filter(|min_items| items.len() < *min_items){
+            self.error("minItems", format!("expected at least {min_items} items, got {}", items.len()));
+        }
+        if let Some(max_items) = rules.max_items.filter(|max_items| items.len() > *max_items){
+            self.error("maxItems", format!("expected at most {max_items} items, got {}", items.len()));
+        }
+        if let Some(schema) = rules.items.as_ref(){
+            for (index, item) in items.into_iter().enumerate(){
+                self.validate_child(schema, item, Segment::Key(index.to_string()));
+            }
+        }
+    }
+
+    fn validate_object(&mut self, rules: &Rules, object: &'p PyAny) {
+        for name in &rules.required{
+            if !object.contains(name).unwrap_or(false){
+                self.error("required", format!("{name:?} is required"));
+            }
+        }
+        for (name, schema) in &rules.properties{
+            if let Ok(value) = object.get_item(name){
+                self.validate_child(schema, value, Segment::Key(name.to_owned()));
+            }
+        }
+        if let Some(schema) = rules.additional_properties.as_ref(){
+            for (key, value) in mapping_items(object){
+                let name = key.to_string();
+                if key.is_instance_of::<PyString>() && rules.properties.iter().any(|(property, _)| *property == name){
+                    continue
+                }
+                match schema {
+                    Schema::Bool(false) => self.error("additionalProperties", format!("additional property {name:?} is not allowed")),
+                    schema => if let Ok(segment) = key_segment(key){
+                        self.validate_child(schema, value, segment)
+                    }
+                }
+            }
+        }
+    }
+}
+
+
+/// Validates `data` against a JSON Schema given as a dict, returning the
+/// list of errors found (empty when valid) as `{"path", "keyword",
+/// "message"}` dicts.
+#[pyfunction]
+pub(crate) fn validate(py: Python, data: &PyAny, schema: &PyAny) -> PyResult<PyObject> {
+    let schema = Schema::compile(py, schema)?;
+    let mut validator = Validator { py, path: vec![], errors: vec![] };
+    validator.validate(&schema, data);
+    let errors = PyList::empty(py);
+    for error in validator.errors{
+        let dict = PyDict::new(py);
+        dict.set_item("path", error.path)?;
+        dict.set_item("keyword", error.keyword)?;
+        dict.set_item("message", error.message)?;
+        errors.append(dict)?;
+    }
+    Ok(errors.to_object(py))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCHEMA: &str = r#"{
+        "type": "object",
+        "required": ["id", "name"],
+        "properties": {
+            "id": {"type": "integer", "minimum": 1},
+            "name": {"type": "string", "minLength": 1, "pattern": "^[a-z]+$"},
+            "tags": {"type": "array", "maxItems": 2, "items": {"enum": ["a", "b"]}},
+            "meta": {"type": "object", "additionalProperties": false, "properties": {"v.1": {"type": ["number", "null"]}}}
+        }
+    }"#;
+
+    fn errors(py: Python, data: &str) -> Vec<(String, String)> {
+        let schema = py.import("json").unwrap().call_method1("loads", (SCHEMA,)).unwrap();
+        let data = py.eval(data, None, None).unwrap();
+        let errors = validate(py, data, schema).unwrap();
+        errors.as_ref(py).iter().unwrap()
+            .map(|error| {
+                let error = error.unwrap();
+                (error.get_item("path").unwrap().extract().unwrap(), error.get_item("keyword").unwrap().extract().unwrap())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn valid_documents(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            assert!(errors(py, "{'id': 1.0, 'name': 'joe', 'tags': ['a'], 'meta': {'v.1': None}}").is_empty());
+        });
+    }
+
+    #[test]
+    fn errors_are_located_by_path(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let found = errors(py, "{'id': True, 'tags': ['a', 'c', 'b'], 'meta': {'v.1': '1', 'x': 0}}");
+            let expected = [
+                ("", "required"),
+                ("id", "type"),
+                ("tags", "maxItems"),
+                ("tags.1", "enum"),
+                (r"meta.v\.1", "type"),
+                ("meta", "additionalProperties")
+            ];
+            assert_eq!(found, expected.map(|(path, keyword)| (path.to_owned(), keyword.to_owned())));
+            let found = errors(py, "{'id': 0, 'name': 'Joe'}");
+            assert_eq!(found, [("id".to_owned(), "minimum".to_owned()), ("name".to_owned(), "pattern".to_owned())]);
+        });
+    }
+
+    #[test]
+    fn any_mapping_and_key_is_located(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let schema = py.eval("{'additionalProperties': {'type': 'string'}}", None, None).unwrap();
+            let data = py.eval("__import__('types').MappingProxyType({'a[0]': 1, 2: 'b', 3: 4, ('x', 'y'): 5})", None, None).unwrap();
+            let errors = validate(py, data, schema).unwrap();
+            let paths: Vec<String> = errors.as_ref(py).iter().unwrap()
+                .map(|error| error.unwrap().get_item("path").unwrap().extract().unwrap())
+                .collect();
+            assert_eq!(paths, [r"a\[0\]", "#3", "('x', 'y')"]);
+        });
+    }
+
+    #[test]
+    fn invalid_schemas(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let data = PyDict::new(py);
+            assert!(validate(py, data, py.eval("{'type': 'text'}", None, None).unwrap()).is_err());
+            assert!(validate(py, data, py.eval("{'pattern': '('}", None, None).unwrap()).is_err());
+        });
+    }
+}
@@ -0,0 +1,71 @@
+//! "Did you mean" suggestions for keys that are not found.
+//!
+//! Sibling keys are suggested when they only differ from the missing one in
+//! case or separators (`first_name`, `first-name`, `FirstName`), or are
+//! within a few edits of it (`adress` for `address`).
+
+/// How many suggestions are made at most.
+const MAX_SUGGESTIONS: usize = 3;
+const SEPARATORS: [char; 4] = ['_', '-', ' ', '.'];
+
+
+fn normalize(key: &str) -> String {
+    key.chars().filter(|c| !SEPARATORS.contains(c)).flat_map(char::to_lowercase).collect()
+}
+
+
+/// Levenshtein distance between `a` and `b`, in characters.
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate(){
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate(){
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+
+/// Keys among `candidates` that `key` was likely meant to be, closest first.
+pub(crate) fn suggestions(key: &str, candidates: impl IntoIterator<Item = String>) -> Vec<String> {
+    let normalized = normalize(key);
+    // typos get more room the longer the key is, and none in short ones
+    let max_distance = match normalized.chars().count() {
+        0..=2 => 0,
+        length => (length / 4).max(1)
+    };
+    let mut ranked: Vec<(usize, String)> = candidates.into_iter()
+        .filter(|candidate| candidate != key)
+        .filter_map(|candidate| {
+            let edits = distance(&normalized, &normalize(&candidate));
+            (edits <= max_distance).then_some((edits, candidate))
+        })
+        .collect();
+    ranked.sort_by_key(|(edits, _)| *edits);
+    ranked.into_iter().take(MAX_SUGGESTIONS).map(|(_, candidate)| candidate).collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn suggest(key: &str, candidates: &[&str]) -> Vec<String> {
+        suggestions(key, candidates.iter().map(|candidate| candidate.to_string()))
+    }
+
+    #[test]
+    fn near_misses_are_suggested(){
+        assert_eq!(suggest("adress", &["name", "address", "city"]), ["address"]);
+        assert_eq!(suggest("first_name", &["FirstName", "last_name"]), ["FirstName"]);
+        assert_eq!(suggest("user-id", &["user_id", "userid", "uid"]), ["user_id", "userid"]);
+        assert_eq!(suggest("City", &["city", "cities"]), ["city"]);
+        assert!(suggest("zip", &["name", "address"]).is_empty());
+        assert!(suggest("d", &["c"]).is_empty());
+    }
+}
@@ -0,0 +1,489 @@
+//! Temporal conversions: `rtype="datetime"`, `"date"`, `"time"` and
+//! `"timedelta"`.
+//!
+//! Strings are parsed as ISO-8601 (RFC 3339 included) values, durations as
+//! ISO-8601 durations or clock durations. Numbers are epoch timestamps or
+//! durations expressed in the requested unit, and so are numeric strings for
+//! durations (a string such as `"2023"` is never read as a timestamp).
+//!
+//! `tz` applies to datetimes found as python objects as well.
+
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Timelike, Utc};
+use pyo3::exceptions::PyValueError;
+use pyo3::types::{PyBool, PyDate, PyDateAccess, PyDateTime, PyDelta, PyDict, PyFloat, PyLong, PyString, PyTime, PyTzInfo};
+use pyo3::{PyAny, PyErr, PyObject, PyResult, Python, ToPyObject};
+
+
+const DATE_FORMATS: [&str; 2] = ["%Y-%m-%d", "%Y%m%d"];
+const TIME_FORMATS: [&str; 3] = ["%H:%M:%S%.f", "%H:%M", "%H%M%S%.f"];
+const MICROS_PER_SECOND: i64 = 1_000_000;
+const MICROS_PER_DAY: i64 = 86_400 * MICROS_PER_SECOND;
+
+
+/// Unit of epoch timestamps and numeric durations.
+#[derive(Clone, Copy)]
+pub(crate) enum Unit{
+    Seconds,
+    Millis,
+    Micros,
+    Nanos
+}
+
+impl Unit {
+    fn per_second(&self) -> i64 {
+        match self {
+            Unit::Seconds => 1,
+            Unit::Millis => 1_000,
+            Unit::Micros => 1_000_000,
+            Unit::Nanos => 1_000_000_000
+        }
+    }
+}
+
+impl TryFrom<&str> for Unit{
+    type Error = PyErr;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "s" => Ok(Unit::Seconds),
+            "ms" => Ok(Unit::Millis),
+            "us" => Ok(Unit::Micros),
+            "ns" => Ok(Unit::Nanos),
+            _ => Err(PyValueError::new_err(format!("invalid unit: {value} (expected s, ms, us or ns)")))
+        }
+    }
+}
+
+
+/// Timezone converted datetimes are expressed in. Naive values are assumed
+/// to be in it.
+pub(crate) enum Timezone{
+    /// Offsets are kept as parsed and naive values stay naive
+    Keep,
+    /// A fixed offset from UTC (`"UTC"`, `"+02:00"`)
+    Fixed(FixedOffset),
+    /// A `zoneinfo.ZoneInfo` zone (`"Europe/Madrid"`)
+    Named(PyObject)
+}
+
+impl Timezone {
+    pub(crate) fn new(py: Python, tz: Option<&str>) -> PyResult<Self> {
+        let Some(tz) = tz else {
+            return Ok(Timezone::Keep)
+        };
+        if let Some(offset) = parse_offset(tz){
+            return Ok(Timezone::Fixed(offset))
+        }
+        let zone = py.import("zoneinfo")?.getattr("ZoneInfo")?.call1((tz,))
+            .map_err(|_| PyValueError::new_err(format!("invalid timezone: {tz}")))?;
+        Ok(Timezone::Named(zone.to_object(py)))
+    }
+}
+
+
+/// `datetime.timezone` for a fixed offset from UTC.
+pub(crate) fn fixed_offset<'p>(py: Python<'p>, seconds: i32) -> PyResult<&'p PyTzInfo> {
+    let offset = PyDelta::new(py, 0, seconds, 0, true)?;
+    let timezone = py.import("datetime")?.getattr("timezone")?.call1((offset,))?;
+    Ok(timezone.downcast::<PyTzInfo>()?)
+}
+
+
+/// Parses `Z`, `UTC` and `±HH:MM`, `±HHMM` or `±HH` offsets.
+fn parse_offset(content: &str) -> Option<FixedOffset> {
+    if content.eq_ignore_ascii_case("z") || content.eq_ignore_ascii_case("utc"){
+        return FixedOffset::east_opt(0)
+    }
+    let (sign, offset) = match content.split_at_checked(1)? {
+        ("+", offset) => (1, offset),
+        ("-", offset) => (-1, offset),
+        _ => return None
+    };
+    let (hours, minutes) = match offset.split_once(':') {
+        Some(parts) => parts,
+        None if offset.len() == 4 => offset.split_at(2),
+        None => (offset, "00")
+    };
+    if hours.len() != 2 || minutes.len() != 2 || !(hours.to_owned() + minutes).chars().all(|c| c.is_ascii_digit()){
+        return None
+    }
+    let seconds = hours.parse::<i32>().ok()? * 3_600 + minutes.parse::<i32>().ok()? * 60;
+    FixedOffset::east_opt(sign * seconds)
+}
+
+
+/// A datetime found in a document.
+enum Parsed{
+    Aware(DateTime<FixedOffset>),
+    Naive(NaiveDateTime)
+}
+
+
+fn parse_date(content: &str) -> Option<NaiveDate> {
+    DATE_FORMATS.iter().find_map(|format| NaiveDate::parse_from_str(content, format).ok())
+}
+
+
+/// Parses a time of day, along with its UTC offset if any.
+fn parse_time(content: &str) -> Option<(NaiveTime, Option<FixedOffset>)> {
+    let (time, offset) = match content.find(['Z', 'z', '+', '-']) {
+        Some(pos) => (content[..pos].trim_end(), Some(parse_offset(&content[pos..])?)),
+        None => (content, None)
+    };
+    let time = TIME_FORMATS.iter().find_map(|format| NaiveTime::parse_from_str(time, format).ok())?;
+    Some((time, offset))
+}
+
+
+/// Parses ISO-8601 datetimes, a bare date standing for its midnight.
+fn parse_datetime(content: &str) -> Option<Parsed> {
+    let Some(separator) = content.find(['T', 't', ' ']) else {
+        return parse_date(content).map(|date| Parsed::Naive(date.and_time(NaiveTime::MIN)))
+    };
+    let date = parse_date(&content[..separator])?;
+    let (time, offset) = parse_time(content[separator + 1..].trim_start())?;
+    let datetime = date.and_time(time);
+    Some(match offset {
+        Some(offset) => Parsed::Aware(offset.from_local_datetime(&datetime).single()?),
+        None => Parsed::Naive(datetime)
+    })
+}
+
+
+/// Parses ISO-8601 durations (`P1DT2H30M`, `PT0.5S`, `P2W`) as microseconds.
+/// Years and months have no fixed length and are rejected.
+fn parse_iso_duration(content: &str) -> Option<f64> {
+    let (date, time) = match content.split_once(['T', 't']) {
+        Some((date, time)) if !time.is_empty() => (date, time),
+        Some(_) => return None,
+        None => (content, "")
+    };
+    let mut micros = 0.0;
+    let mut components = 0;
+    for (part, designators) in [(date, &[('W', 7 * 86_400), ('D', 86_400)][..]), (time, &[('H', 3_600), ('M', 60), ('S', 1)][..])]{
+        let mut rest = part;
+        for (designator, seconds) in designators{
+            if let Some(pos) = rest.find(*designator){
+                let amount = rest[..pos].replace(',', ".");
+                if amount.is_empty() || !amount.chars().all(|c| c.is_ascii_digit() || c == '.'){
+                    return None
+                }
+                micros += amount.parse::<f64>().ok()? * (*seconds * MICROS_PER_SECOND) as f64;
+                components += 1;
+                rest = &rest[pos + 1..];
+            }
+        }
+        if !rest.is_empty(){
+            return None
+        }
+    }
+    (components > 0).then_some(micros)
+}
+
+
+/// Parses clock durations as printed by python (`2:30:00`,
+/// `-1 day, 23:00:00`) as microseconds.
+fn parse_clock_duration(content: &str) -> Option<f64> {
+    let (days, clock) = match content.split_once(',') {
+        Some((days, clock)) => {
+            let days = days.trim().trim_end_matches("days").trim_end_matches("day").trim();
+            (days.parse::<i64>().ok()?, clock.trim())
+        },
+        None => (0, content)
+    };
+    let (negative, clock) = match clock.strip_prefix('-') {
+        Some(clock) => (true, clock),
+        None => (false, clock)
+    };
+    let mut parts = clock.split(':');
+    let hours = parts.next()?.parse::<u32>().ok()?;
+    let minutes = parts.next()?.parse::<u32>().ok().filter(|m| *m < 60)?;
+    let seconds = match parts.next() {
+        Some(seconds) => seconds.parse::<f64>().ok().filter(|s| (0.0..60.0).contains(s))?,
+        None => 0.0
+    };
+    if parts.next().is_some(){
+        return None
+    }
+    let clock = (f64::from(hours) * 3_600.0 + f64::from(minutes) * 60.0 + seconds) * MICROS_PER_SECOND as f64;
+    Some((days * MICROS_PER_DAY) as f64 + if negative { -clock } else { clock })
+}
+
+
+fn parse_duration(content: &str) -> Option<f64> {
+    let (negative, unsigned) = match content.strip_prefix('-') {
+        Some(unsigned) => (true, unsigned),
+        None => (false, content.strip_prefix('+').unwrap_or(content))
+    };
+    match unsigned.strip_prefix(['P', 'p']) {
+        Some(designators) => parse_iso_duration(&designators.to_ascii_uppercase()).map(|micros| if negative { -micros } else { micros }),
+        None => parse_clock_duration(content)
+    }
+}
+
+
+/// An epoch timestamp or numeric duration.
+enum Number{
+    Int(i64),
+    Float(f64)
+}
+
+impl Number {
+    /// The number `value` is, numeric strings included when `strings`.
+    fn new(value: &PyAny, strings: bool) -> Option<Self> {
+        if value.is_instance_of::<PyBool>(){
+            None
+        }else if value.is_instance_of::<PyLong>(){
+            value.extract().ok().map(Number::Int)
+        }else if let Ok(number) = value.downcast::<PyFloat>(){
+            Some(Number::Float(number.value()))
+        }else if let Some(content) = value.downcast::<PyString>().ok().filter(|_| strings){
+            let content = content.to_str().ok()?.trim();
+            content.parse().map(Number::Int).or_else(|_| content.parse().map(Number::Float)).ok()
+        }else{
+            None
+        }
+    }
+
+    fn micros(&self, unit: Unit) -> Option<f64> {
+        let micros = match self {
+            Number::Int(number) => *number as f64 * MICROS_PER_SECOND as f64 / unit.per_second() as f64,
+            Number::Float(number) => number * MICROS_PER_SECOND as f64 / unit.per_second() as f64
+        };
+        micros.is_finite().then_some(micros)
+    }
+
+    fn timestamp(&self, unit: Unit) -> Option<DateTime<Utc>> {
+        let per_second = unit.per_second();
+        match self {
+            Number::Int(number) => {
+                let nanos = number.rem_euclid(per_second) * (1_000_000_000 / per_second);
+                DateTime::from_timestamp(number.div_euclid(per_second), nanos as u32)
+            },
+            Number::Float(number) => {
+                let seconds = number / per_second as f64;
+                if !seconds.is_finite(){
+                    return None
+                }
+                let nanos = ((seconds - seconds.floor()) * 1e9).round().min(999_999_999.0);
+                DateTime::from_timestamp(seconds.floor() as i64, nanos as u32)
+            }
+        }
+    }
+}
+
+
+fn naive_to_py<'p>(py: Python<'p>, datetime: &NaiveDateTime, tzinfo: Option<&PyTzInfo>) -> PyResult<&'p PyAny> {
+    let datetime = PyDateTime::new(py,
+        datetime.year(), datetime.month() as u8, datetime.day() as u8,
+        datetime.hour() as u8, datetime.minute() as u8, datetime.second() as u8,
+        (datetime.nanosecond() / 1_000).min(999_999),
+        tzinfo
+    )?;
+    Ok(datetime.into())
+}
+
+
+fn datetime_to_py<'p>(py: Python<'p>, parsed: Parsed, timezone: &Timezone) -> PyResult<&'p PyAny> {
+    match (parsed, timezone) {
+        (Parsed::Aware(datetime), Timezone::Keep) => {
+            let tzinfo = fixed_offset(py, datetime.offset().local_minus_utc())?;
+            naive_to_py(py, &datetime.naive_local(), Some(tzinfo))
+        },
+        (Parsed::Naive(datetime), Timezone::Keep) => naive_to_py(py, &datetime, None),
+        (Parsed::Aware(datetime), Timezone::Fixed(offset)) => {
+            let tzinfo = fixed_offset(py, offset.local_minus_utc())?;
+            naive_to_py(py, &datetime.with_timezone(offset).naive_local(), Some(tzinfo))
+        },
+        (Parsed::Naive(datetime), Timezone::Fixed(offset)) => {
+            naive_to_py(py, &datetime, Some(fixed_offset(py, offset.local_minus_utc())?))
+        },
+        (Parsed::Aware(datetime), Timezone::Named(zone)) => {
+            let utc = naive_to_py(py, &datetime.naive_utc(), Some(pyo3::types::timezone_utc(py)))?;
+            utc.call_method1("astimezone", (zone,))
+        },
+        (Parsed::Naive(datetime), Timezone::Named(zone)) => {
+            let kwargs = PyDict::new(py);
+            kwargs.set_item("tzinfo", zone)?;
+            naive_to_py(py, &datetime, None)?.call_method("replace", (), Some(kwargs))
+        }
+    }
+}
+
+
+/// `datetime` converted into `timezone`, the way parsed ones are.
+fn localize<'p>(py: Python<'p>, datetime: &'p PyAny, timezone: &Timezone) -> PyResult<&'p PyAny> {
+    let tzinfo: &PyAny = match timezone {
+        Timezone::Keep => return Ok(datetime),
+        Timezone::Fixed(offset) => fixed_offset(py, offset.local_minus_utc())?,
+        Timezone::Named(zone) => zone.as_ref(py)
+    };
+    if datetime.call_method0("utcoffset")?.is_none(){
+        let kwargs = PyDict::new(py);
+        kwargs.set_item("tzinfo", tzinfo)?;
+        return datetime.call_method("replace", (), Some(kwargs))
+    }
+    datetime.call_method1("astimezone", (tzinfo,))
+}
+
+
+pub(crate) fn to_datetime<'p>(py: Python<'p>, value: &'p PyAny, unit: Unit, timezone: &Timezone) -> Option<&'p PyAny> {
+    if value.is_instance_of::<PyDateTime>(){
+        return localize(py, value, timezone).ok()
+    }else if let Ok(date) = value.downcast::<PyDate>(){
+        // dates are naive midnights, like ISO-8601 dates
+        let date = NaiveDate::from_ymd_opt(date.get_year(), date.get_month().into(), date.get_day().into())?;
+        return datetime_to_py(py, Parsed::Naive(date.and_time(NaiveTime::MIN)), timezone).ok()
+    }
+    let parsed = value.downcast::<PyString>().ok()
+        .and_then(|content| parse_datetime(content.to_str().ok()?.trim()))
+        .or_else(|| Some(Parsed::Aware(Number::new(value, false)?.timestamp(unit)?.fixed_offset())))?;
+    datetime_to_py(py, parsed, timezone).ok()
+}
+
+
+pub(crate) fn to_date<'p>(py: Python<'p>, value: &'p PyAny, unit: Unit, timezone: &Timezone) -> Option<&'p PyAny> {
+    if value.is_instance_of::<PyDateTime>(){
+        return localize(py, value, timezone).ok()?.call_method0("date").ok()
+    }else if value.is_instance_of::<PyDate>(){
+        return Some(value)
+    }
+    let date = value.downcast::<PyString>().ok().and_then(|content| parse_date(content.to_str().ok()?.trim()));
+    match date {
+        Some(date) => PyDate::new(py, date.year(), date.month() as u8, date.day() as u8).ok().map(Into::into),
+        None => to_datetime(py, value, unit, timezone)?.call_method0("date").ok()
+    }
+}
+
+
+pub(crate) fn to_time<'p>(py: Python<'p>, value: &'p PyAny, unit: Unit, timezone: &Timezone) -> Option<&'p PyAny> {
+    if value.is_instance_of::<PyDateTime>(){
+        return localize(py, value, timezone).ok()?.call_method0("timetz").ok()
+    }else if value.is_instance_of::<PyTime>(){
+        return Some(value)
+    }
+    let time = value.downcast::<PyString>().ok().and_then(|content| parse_time(content.to_str().ok()?.trim()));
+    match time {
+        Some((time, offset)) => {
+            let tzinfo = match offset {
+                Some(offset) => Some(fixed_offset(py, offset.local_minus_utc()).ok()?),
+                None => None
+            };
+            PyTime::new(py,
+                time.hour() as u8, time.minute() as u8, time.second() as u8, (time.nanosecond() / 1_000).min(999_999),
+                tzinfo
+            ).ok().map(Into::into)
+        },
+        None => to_datetime(py, value, unit, timezone)?.call_method0("timetz").ok()
+    }
+}
+
+
+pub(crate) fn to_timedelta<'p>(py: Python<'p>, value: &'p PyAny, unit: Unit) -> Option<&'p PyAny> {
+    if value.is_instance_of::<PyDelta>(){
+        return Some(value)
+    }
+    let micros = value.downcast::<PyString>().ok()
+        .and_then(|content| parse_duration(content.to_str().ok()?.trim()))
+        .or_else(|| Number::new(value, true)?.micros(unit))?
+        .round();
+    if micros.abs() >= i64::MAX as f64{
+        return None
+    }
+    let micros = micros as i64;
+    let days = i32::try_from(micros.div_euclid(MICROS_PER_DAY)).ok()?;
+    let rest = micros.rem_euclid(MICROS_PER_DAY);
+    let delta = PyDelta::new(py, days, (rest / MICROS_PER_SECOND) as i32, (rest % MICROS_PER_SECOND) as i32, false).ok()?;
+    Some(delta.into())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Converter = for<'p> fn(Python<'p>, &'p PyAny) -> Option<&'p PyAny>;
+
+    fn convert(py: Python, value: &str, converter: Converter) -> String {
+        let value = py.eval(value, None, None).unwrap();
+        converter(py, value).map_or_else(|| "-".to_owned(), |converted| converted.repr().unwrap().to_string())
+    }
+
+    fn datetime<'p>(py: Python<'p>, value: &'p PyAny) -> Option<&'p PyAny> {
+        to_datetime(py, value, Unit::Seconds, &Timezone::Keep)
+    }
+
+    fn datetime_millis<'p>(py: Python<'p>, value: &'p PyAny) -> Option<&'p PyAny> {
+        to_datetime(py, value, Unit::Millis, &Timezone::Keep)
+    }
+
+    fn date<'p>(py: Python<'p>, value: &'p PyAny) -> Option<&'p PyAny> {
+        to_date(py, value, Unit::Seconds, &Timezone::Keep)
+    }
+
+    fn time<'p>(py: Python<'p>, value: &'p PyAny) -> Option<&'p PyAny> {
+        to_time(py, value, Unit::Seconds, &Timezone::Keep)
+    }
+
+    fn timedelta<'p>(py: Python<'p>, value: &'p PyAny) -> Option<&'p PyAny> {
+        to_timedelta(py, value, Unit::Seconds)
+    }
+
+    #[test]
+    fn datetimes_from_iso_strings_and_epochs(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            assert_eq!(convert(py, "'2023-07-01T10:30:00.25+02:00'", datetime),
+                "datetime.datetime(2023, 7, 1, 10, 30, 0, 250000, tzinfo=datetime.timezone(datetime.timedelta(seconds=7200)))");
+            assert_eq!(convert(py, "'2023-07-01 10:30Z'", datetime),
+                "datetime.datetime(2023, 7, 1, 10, 30, tzinfo=datetime.timezone.utc)");
+            assert_eq!(convert(py, "'2023-07-01'", datetime), "datetime.datetime(2023, 7, 1, 0, 0)");
+            assert_eq!(convert(py, "__import__('datetime').date(2023, 7, 1)", datetime), "datetime.datetime(2023, 7, 1, 0, 0)");
+            assert_eq!(convert(py, "1688207400", datetime), "datetime.datetime(2023, 7, 1, 10, 30, tzinfo=datetime.timezone.utc)");
+            assert_eq!(convert(py, "'2023-13-01'", datetime), "-");
+            assert_eq!(convert(py, "'2023'", datetime), "-");
+            assert_eq!(convert(py, "'1688207400'", datetime), "-");
+            assert_eq!(convert(py, "1688207400500", datetime_millis),
+                "datetime.datetime(2023, 7, 1, 10, 30, 0, 500000, tzinfo=datetime.timezone.utc)");
+        });
+    }
+
+    #[test]
+    fn datetimes_in_timezone(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let utc = Timezone::new(py, Some("UTC")).unwrap();
+            let converted = to_datetime(py, py.eval("'2023-07-01T10:30:00+02:00'", None, None).unwrap(), Unit::Seconds, &utc).unwrap();
+            assert_eq!(converted.call_method0("isoformat").unwrap().to_string(), "2023-07-01T08:30:00+00:00");
+            let offset = Timezone::new(py, Some("-05:00")).unwrap();
+            let converted = to_datetime(py, py.eval("'2023-07-01 10:30'", None, None).unwrap(), Unit::Seconds, &offset).unwrap();
+            assert_eq!(converted.call_method0("isoformat").unwrap().to_string(), "2023-07-01T10:30:00-05:00");
+            assert!(Timezone::new(py, Some("Not/AZone")).is_err());
+            let found = py.eval("__import__('datetime').datetime(2023, 7, 1, 10, 30, tzinfo=__import__('datetime').timezone.utc)", None, None).unwrap();
+            let converted = to_datetime(py, found, Unit::Seconds, &offset).unwrap();
+            assert_eq!(converted.call_method0("isoformat").unwrap().to_string(), "2023-07-01T05:30:00-05:00");
+            let found = py.eval("__import__('datetime').datetime(2023, 7, 1, 10, 30)", None, None).unwrap();
+            let converted = to_datetime(py, found, Unit::Seconds, &utc).unwrap();
+            assert_eq!(converted.call_method0("isoformat").unwrap().to_string(), "2023-07-01T10:30:00+00:00");
+            let found = py.eval("__import__('datetime').date(2023, 7, 1)", None, None).unwrap();
+            let converted = to_datetime(py, found, Unit::Seconds, &offset).unwrap();
+            assert_eq!(converted.call_method0("isoformat").unwrap().to_string(), "2023-07-01T00:00:00-05:00");
+        });
+    }
+
+    #[test]
+    fn dates_times_and_durations(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            assert_eq!(convert(py, "'2023-07-01'", date), "datetime.date(2023, 7, 1)");
+            assert_eq!(convert(py, "'2023-07-01T23:30:00'", date), "datetime.date(2023, 7, 1)");
+            assert_eq!(convert(py, "'07:32'", time), "datetime.time(7, 32)");
+            assert_eq!(convert(py, "'P1DT2H30M'", timedelta), "datetime.timedelta(days=1, seconds=9000)");
+            assert_eq!(convert(py, "'-PT0.5S'", timedelta), "datetime.timedelta(days=-1, seconds=86399, microseconds=500000)");
+            assert_eq!(convert(py, "'-1 day, 23:00:00'", timedelta), "datetime.timedelta(days=-1, seconds=82800)");
+            assert_eq!(convert(py, "90", timedelta), "datetime.timedelta(seconds=90)");
+            assert_eq!(convert(py, "'90'", timedelta), "datetime.timedelta(seconds=90)");
+            assert_eq!(convert(py, "'P1M'", timedelta), "-");
+        });
+    }
+}
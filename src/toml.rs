@@ -0,0 +1,149 @@
+//! TOML documents (behind the `toml` feature).
+//!
+//! Documents are loaded into python objects, keeping datetimes as `datetime`
+//! objects: offset datetimes are timezone aware, local datetimes are naive
+//! and local dates/times load as `date`/`time`. Arrays of tables load as
+//! lists, so `[[bin]]` entries are addressed as `bin.0.name`.
+
+use ::toml::value::{Datetime, Offset};
+use ::toml::Value;
+use pyo3::exceptions::PyValueError;
+use pyo3::types::{PyDate, PyDateTime, PyDict, PyList, PyTime};
+use pyo3::{pyfunction, PyAny, PyObject, PyResult, Python, ToPyObject};
+
+use crate::temporal::fixed_offset;
+use crate::{dictor, read_text_or_path, PathArg, RType};
+
+
+fn datetime_to_py(py: Python, datetime: &Datetime) -> PyResult<PyObject> {
+    let tzinfo = match datetime.offset {
+        Some(Offset::Z) => Some(pyo3::types::timezone_utc(py)),
+        Some(Offset::Custom { minutes }) => Some(fixed_offset(py, i32::from(minutes) * 60)?),
+        None => None
+    };
+    Ok(match (&datetime.date, &datetime.time) {
+        (Some(date), Some(time)) => PyDateTime::new(py,
+            date.year.into(), date.month, date.day,
+            time.hour, time.minute, time.second.unwrap_or(0), time.nanosecond.unwrap_or(0) / 1_000,
+            tzinfo
+        )?.to_object(py),
+        (Some(date), None) => PyDate::new(py, date.year.into(), date.month, date.day)?.to_object(py),
+        (None, Some(time)) => PyTime::new(py,
+            time.hour, time.minute, time.second.unwrap_or(0), time.nanosecond.unwrap_or(0) / 1_000,
+            tzinfo
+        )?.to_object(py),
+        (None, None) => py.None()
+    })
+}
+
+
+fn to_py(py: Python, value: &Value) -> PyResult<PyObject> {
+    Ok(match value {
+        Value::String(s) => s.to_object(py),
+        Value::Integer(i) => i.to_object(py),
+        Value::Float(f) => f.to_object(py),
+        Value::Boolean(b) => b.to_object(py),
+        Value::Datetime(datetime) => datetime_to_py(py, datetime)?,
+        Value::Array(list) => {
+            let items = list.iter().map(|item| to_py(py, item)).collect::<PyResult<Vec<_>>>()?;
+            PyList::new(py, items).to_object(py)
+        },
+        Value::Table(table) => {
+            let dict = PyDict::new(py);
+            for (key, item) in table{
+                dict.set_item(key, to_py(py, item)?)?;
+            }
+            dict.to_object(py)
+        }
+    })
+}
+
+
+/// Loads a TOML text (or file) into python objects.
+#[pyfunction]
+pub(crate) fn load_toml(py: Python, source: &PyAny) -> PyResult<PyObject> {
+    let text = read_text_or_path(source)?;
+    let table: ::toml::Table = ::toml::from_str(&text).map_err(|e| PyValueError::new_err(format!("invalid TOML: {e}")))?;
+    to_py(py, &Value::Table(table))
+}
+
+
+/// Same as `dictor` but takes a TOML text or file.
+#[pyfunction]
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn dictor_toml(py: Python,
+    source: &PyAny,
+    path: Option<PathArg>,
+    default: Option<PyObject>,
+    checknone: Option<bool>,
+    ignorecase: Option<bool>,
+    pathsep: Option<String>,
+    search: Option<String>,
+    rtype: Option<RType>,
+    rounding: Option<&str>,
+    strict: Option<bool>,
+    unit: Option<&str>,
+    tz: Option<&str>,
+    on_error: Option<&str>
+) -> PyResult<Option<PyObject>> {
+    let data = load_toml(py, source)?;
+    dictor(py, data.as_ref(py), path, default, checknone, ignorecase, pathsep, search, rtype, rounding, strict, unit, tz, on_error, None)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use pyo3::types::PyString;
+
+    use super::*;
+
+    const MANIFEST: &str = r#"
+[package]
+name = "dicto_r"
+released = 2023-07-01T10:30:00.250+02:00
+built = 2023-07-01T10:30:00
+date = 2023-07-01
+at = 07:32:00
+
+[[bin]]
+name = "dictor"
+
+[[bin]]
+name = "other"
+"#;
+
+    fn lookup(py: Python, path: &str) -> PyObject {
+        let source = PyString::new(py, MANIFEST);
+        dictor_toml(py, source, Some(path.into()), None, None, None, None, None, None, None, None, None, None, None).unwrap().unwrap()
+    }
+
+    #[test]
+    fn array_of_tables_by_index(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            assert_eq!(lookup(py, "bin.1.name").extract::<String>(py).unwrap(), "other");
+            assert_eq!(lookup(py, "bin.-1.name").extract::<String>(py).unwrap(), "other");
+        });
+    }
+
+    #[test]
+    fn datetimes_are_python_objects(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let released = lookup(py, "package.released");
+            let released = released.as_ref(py);
+            assert!(released.downcast::<PyDateTime>().is_ok());
+            assert_eq!(released.call_method0("isoformat").unwrap().to_string(), "2023-07-01T10:30:00.250000+02:00");
+
+            let built = lookup(py, "package.built");
+            assert!(built.as_ref(py).getattr("tzinfo").unwrap().is_none());
+
+            let date = lookup(py, "package.date");
+            assert!(date.as_ref(py).downcast::<PyDate>().is_ok());
+            assert!(date.as_ref(py).downcast::<PyDateTime>().is_err());
+
+            let at = lookup(py, "package.at");
+            assert_eq!(at.as_ref(py).to_string(), "07:32:00");
+        });
+    }
+}
@@ -0,0 +1,341 @@
+//! A single step of the traversal `dictor` performs: looking a path segment
+//! up in the value reached so far.
+//!
+//! Steps record how the value was looked up, so `explain` can tell why a
+//! path did (or did not) resolve. Dicts and lists are told apart first, and
+//! what is only needed to report failures is left to `Step::reason`.
+
+use pyo3::types::{PyByteArray, PyBytes, PyDict, PyFloat, PyList, PyLong, PyMapping, PySequence, PyString, PyTuple};
+use pyo3::{pyfunction, PyAny, PyObject, PyResult, Python, ToPyObject};
+
+use crate::path::{Segment, Slice};
+use crate::{type_name, PathArg};
+
+
+pub(crate) enum Outcome<'p>{
+    Found(&'p PyAny),
+    /// The value reached is not a container
+    NotContainer,
+    Missing
+}
+
+
+pub(crate) struct Step<'p>{
+    /// Whether the value the segment was looked up in is a mapping (see
+    /// `is_mapping`)
+    pub(crate) mapping: bool,
+    /// Python key looked up last (an int when tried as a list index)
+    pub(crate) key: Option<PyObject>,
+    /// Key matching the segment when ignoring case
+    pub(crate) ignorecase_key: Option<String>,
+    /// Whether the segment was tried as an index first, then as a string key
+    pub(crate) int_fallback: bool,
+    /// Whether the segment was looked up as an attribute
+    pub(crate) attribute: bool,
+    pub(crate) outcome: Outcome<'p>
+}
+
+impl Step<'_> {
+    /// Key looked up last, `segment` (the one this step looked up) or the
+    /// key it matched ignoring case.
+    pub(crate) fn arg(&self, segment: &Segment) -> String {
+        self.ignorecase_key.clone().unwrap_or_else(|| segment.to_string())
+    }
+
+    /// Why `segment` did not resolve in `container`, if it did not.
+    pub(crate) fn reason(&self, container: &PyAny, segment: &Segment, ignorecase: bool) -> Option<String> {
+        if let Outcome::Found(_) = self.outcome {
+            return None
+        }
+        let container = type_name(container).unwrap_or_else(|| "?".into());
+        let arg = self.arg(segment);
+        Some(match self.outcome {
+            Outcome::NotContainer => format!("{container} is not a container"),
+            _ if self.attribute => format!("no data attribute {arg:?} on {container}"),
+            _ if ignorecase && self.ignorecase_key.is_none() && self.mapping => {
+                format!("no key matching {arg:?} ignoring case in {container}")
+            },
+            _ => format!("no {arg:?} in {container}")
+        })
+    }
+}
+
+
+/// Whether `value` is a `str`, `int` (or `bool`), `float` or `None`: the
+/// leaves most documents are made of, never containers.
+pub(crate) fn is_scalar(value: &PyAny) -> bool {
+    value.is_none() || value.is_instance_of::<PyString>() || value.is_instance_of::<PyLong>() || value.is_instance_of::<PyFloat>()
+}
+
+
+/// Whether `value` is of a builtin type `is_mapping` and `is_sequence` tell
+/// apart without the `collections.abc` checks.
+fn is_builtin(value: &PyAny) -> bool {
+    value.is_exact_instance_of::<PyList>() || value.is_exact_instance_of::<PyTuple>() || is_scalar(value)
+}
+
+
+/// Whether `value` is looked up by key: a dict or any other
+/// `collections.abc.Mapping` (`MappingProxyType`, `ChainMap`, `UserDict`...).
+pub(crate) fn is_mapping(value: &PyAny) -> bool {
+    value.is_instance_of::<PyDict>() || !is_builtin(value) && value.downcast::<PyMapping>().is_ok()
+}
+
+
+/// Whether `value` is looked up by index: a list or any other
+/// `collections.abc.Sequence` but strings and bytes.
+pub(crate) fn is_sequence(value: &PyAny) -> bool {
+    if value.is_instance_of::<PyList>() || value.is_exact_instance_of::<PyTuple>(){
+        return true
+    }
+    !is_scalar(value) && !value.is_instance_of::<PyBytes>() &&
+        !value.is_instance_of::<PyByteArray>() && value.downcast::<PySequence>().is_ok()
+}
+
+
+/// Keys of a mapping (see `is_mapping`), in iteration order.
+pub(crate) fn mapping_keys(mapping: &PyAny) -> Vec<&PyAny> {
+    if let Ok(dict) = mapping.downcast::<PyDict>(){
+        return dict.keys().iter().collect()
+    }
+    let keys = mapping.downcast::<PyMapping>().ok().and_then(|mapping| mapping.keys().ok()?.iter().ok());
+    keys.map(|keys| keys.flatten().collect()).unwrap_or_default()
+}
+
+
+/// Items of a mapping (see `is_mapping`), in iteration order.
+pub(crate) fn mapping_items(mapping: &PyAny) -> Vec<(&PyAny, &PyAny)> {
+    if let Ok(dict) = mapping.downcast::<PyDict>(){
+        return dict.iter().collect()
+    }
+    mapping_keys(mapping).into_iter()
+        .filter_map(|key| Some((key, mapping.get_item(key).ok()?)))
+        .collect()
+}
+
+
+/// Whether `getattr` looks attributes of `value` up: instances of classes
+/// defined outside of `builtins`, so neither `None` nor strings, numbers,
+/// bytes and the like.
+fn has_attributes(value: &PyAny) -> bool {
+    value.get_type().getattr("__module__")
+        .and_then(|module| module.extract::<&str>().map(|module| module != "builtins"))
+        .unwrap_or(false)
+}
+
+
+/// The python `slice` a slice segment stands for.
+pub(crate) fn py_slice<'p>(py: Python<'p>, slice: &Slice) -> PyResult<&'p PyAny> {
+    py.import("builtins")?.getattr("slice")?.call1((slice.start, slice.stop, slice.step))
+}
+
+
+/// Completes `step` looking `key` up as is.
+fn exact<'p>(py: Python<'p>, mut step: Step<'p>, inner_object: &'p PyAny, key: PyObject) -> Step<'p> {
+    if let Ok(item) = inner_object.get_item(key.as_ref(py)){
+        step.outcome = Outcome::Found(item);
+    }
+    step.key = Some(key);
+    step
+}
+
+
+/// Looks `segment` up in `inner_object`.
+///
+/// Integer segments are tried as sequence indexes first and then as string
+/// keys. Integer (`#42`) and raw keys are looked up as is.
+///
+/// With `getattr`, keys are looked up as attributes of objects that are
+/// neither mappings nor sequences (see `has_attributes`). Private attributes
+/// (starting with `_`) and callable ones (methods) are never found.
+pub(crate) fn step<'p>(py: Python<'p>, inner_object: &'p PyAny, segment: &Segment, ignorecase: bool, getattr: bool) -> Step<'p> {
+    let mapping = is_mapping(inner_object);
+    let mut step = Step {
+        mapping,
+        key: None,
+        ignorecase_key: None,
+        int_fallback: false,
+        attribute: false,
+        outcome: Outcome::Missing
+    };
+    if !mapping && !is_sequence(inner_object){
+        match segment {
+            Segment::Key(key) if getattr && !key.starts_with('_') && has_attributes(inner_object) => {
+                step.attribute = true;
+                step.key = Some(key.to_object(py));
+                if let Some(item) = inner_object.getattr(key.as_str()).ok().filter(|item| !item.is_callable()){
+                    step.outcome = Outcome::Found(item);
+                }
+            },
+            _ => step.outcome = Outcome::NotContainer
+        }
+        return step
+    }
+
+    let mut arg = match segment {
+        Segment::Key(key) => key.as_str(),
+        Segment::Int(int) => return exact(py, step, inner_object, int.to_object(py)),
+        Segment::Raw(key) => return exact(py, step, inner_object, key.object.clone_ref(py)),
+        Segment::Slice(slice) => match py_slice(py, slice) {
+            Ok(slice) => return exact(py, step, inner_object, slice.to_object(py)),
+            Err(_) => return step
+        }
+    };
+    if ignorecase && mapping{
+        let lowered_arg = arg.to_lowercase();
+        let cased_key = mapping_keys(inner_object).into_iter()
+            .map(|k| k.to_string())
+            .find(|k| k.to_lowercase() == lowered_arg);
+        match cased_key {
+            Some(key) => step.ignorecase_key = Some(key),
+            None => return step
+        }
+        arg = step.ignorecase_key.as_deref().unwrap_or(arg);
+    }
+    let mut inner_item = None;
+    if let Ok(num_arg) = arg.parse::<i32>(){
+        let key = num_arg.to_object(py);
+        inner_item = inner_object.get_item(key.as_ref(py)).ok();
+        step.int_fallback = inner_item.is_none();
+        step.key = Some(key);
+    }
+    if inner_item.is_none(){
+        let key = PyString::new(py, arg);
+        inner_item = inner_object.get_item(key).ok();
+        step.key = Some(key.into());
+    }
+    if let Some(item) = inner_item{
+        step.outcome = Outcome::Found(item);
+    }
+    step
+}
+
+
+/// Traces how `path` resolves in `data`, returning a dict per segment
+/// looked up, up to the one resolution stopped at.
+///
+/// Each dict holds the `segment`, the type of the `container` it was looked
+/// up in, the python `key` tried last, the `ignorecase_key` it matched,
+/// whether the `int_fallback` (integer segments tried as a string key after
+/// failing as an index) was used, whether it was looked up as an
+/// `attribute`, whether it was `found`, the type of the `value` found and the
+/// `reason` it was not.
+#[pyfunction]
+pub(crate) fn explain(py: Python, data: &PyAny, path: PathArg, ignorecase: Option<bool>, pathsep: Option<String>, getattr: Option<bool>) -> PyResult<PyObject> {
+    let ignorecase = ignorecase.unwrap_or(false);
+    let getattr = getattr.unwrap_or(false);
+    let input = path.input(py, pathsep)?;
+    let steps = PyList::empty(py);
+    let mut inner_object = data;
+    for segment in input.segments(){
+        let step = step(py, inner_object, segment, ignorecase, getattr);
+        let record = PyDict::new(py);
+        record.set_item("segment", segment.to_string())?;
+        record.set_item("container", type_name(inner_object))?;
+        record.set_item("key", &step.key)?;
+        record.set_item("ignorecase_key", &step.ignorecase_key)?;
+        record.set_item("int_fallback", step.int_fallback)?;
+        record.set_item("attribute", step.attribute)?;
+        record.set_item("found", matches!(step.outcome, Outcome::Found(_)))?;
+        let value_type = match step.outcome {
+            Outcome::Found(item) => type_name(item),
+            _ => None
+        };
+        record.set_item("value", value_type)?;
+        record.set_item("reason", step.reason(inner_object, segment, ignorecase))?;
+        steps.append(record)?;
+        match step.outcome {
+            Outcome::Found(item) => inner_object = item,
+            _ => break
+        }
+    }
+    Ok(steps.to_object(py))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trace(py: Python, path: &str, ignorecase: bool) -> Vec<(bool, bool, Option<String>)> {
+        let data = py.eval("{'movies': [{'Name': 'alien'}], '1': {'2': 3}}", None, None).unwrap();
+        let steps = explain(py, data, path.into(), Some(ignorecase), None, None).unwrap();
+        steps.as_ref(py).iter().unwrap()
+            .map(|step| {
+                let step = step.unwrap();
+                let field = |name: &str| step.get_item(name).unwrap();
+                (field("found").extract().unwrap(), field("int_fallback").extract().unwrap(), field("reason").extract().unwrap())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn steps_record_how_segments_resolve(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            assert_eq!(trace(py, "movies.0.name", true), [(true, false, None), (true, false, None), (true, false, None)]);
+            assert_eq!(trace(py, "1.2", false), [(true, true, None), (true, true, None)]);
+            let steps = trace(py, "movies.0.name.first", false);
+            assert_eq!(steps.len(), 3);
+            assert_eq!(steps[2].2.as_deref(), Some(r#"no "name" in dict"#));
+            let steps = trace(py, "movies.0.Name.first", false);
+            assert_eq!(steps[3].2.as_deref(), Some("str is not a container"));
+            let steps = trace(py, "movies.0.title", true);
+            assert_eq!(steps[2].2.as_deref(), Some(r#"no key matching "title" ignoring case in dict"#));
+        });
+    }
+
+    #[test]
+    fn mappings_and_sequences_are_walked(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let data = py.eval(
+                "__import__('types').MappingProxyType({'a': ({'B': 1}, __import__('collections').UserDict(c=2))})",
+                None, None).unwrap();
+            let resolve = |path: &str| {
+                let mut inner_object = data;
+                for segment in crate::path::parse(path, Some(".")).unwrap(){
+                    match step(py, inner_object, &segment, true, false).outcome {
+                        Outcome::Found(item) => inner_object = item,
+                        _ => return None
+                    }
+                }
+                Some(inner_object.to_string())
+            };
+            assert_eq!(resolve("a.0.b").as_deref(), Some("1"));
+            assert_eq!(resolve("a.1.c").as_deref(), Some("2"));
+            assert_eq!(resolve("a[1:].0.c").as_deref(), Some("2"));
+            assert_eq!(resolve("a.0.b.0"), None);
+            assert!(!is_sequence(PyString::new(py, "abc")));
+        });
+    }
+
+    #[test]
+    fn attributes_are_looked_up_with_getattr(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let data = py.eval("{'customer': type('Customer', (), {'name': 'ana', '_id': 1, 'greet': lambda self: 'hi'})()}", None, None).unwrap();
+            let resolve = |path: &str, getattr: bool| {
+                let mut inner_object = data;
+                let mut outcome = String::new();
+                for segment in crate::path::parse(path, Some(".")).unwrap(){
+                    let step = step(py, inner_object, &segment, false, getattr);
+                    match step.outcome {
+                        Outcome::Found(item) => inner_object = item,
+                        _ => return step.reason(inner_object, &segment, false).unwrap()
+                    }
+                    outcome = inner_object.to_string();
+                }
+                outcome
+            };
+            assert_eq!(resolve("customer.name", true), "ana");
+            assert_eq!(resolve("customer.name", false), "Customer is not a container");
+            assert_eq!(resolve("customer.age", true), r#"no data attribute "age" on Customer"#);
+            assert_eq!(resolve("customer._id", true), "Customer is not a container");
+            // methods and attributes of builtin values are never looked up
+            assert_eq!(resolve("customer.greet", true), r#"no data attribute "greet" on Customer"#);
+            assert_eq!(resolve("customer.name.upper", true), "str is not a container");
+            assert_eq!(resolve("customer.name.__class__", true), "str is not a container");
+        });
+    }
+}
@@ -0,0 +1,99 @@
+//! Path resolution over documents decoded in Rust (MessagePack, CBOR).
+//!
+//! Paths are walked over the decoded values before any python object is
+//! built, so only the subtree found is converted. The walk stops at the first
+//! segment it cannot resolve exactly the way `traverse::step` would on the
+//! converted document, `dictor` carrying on from there (and reporting misses).
+
+use crate::json::list_position;
+use crate::path::Segment;
+
+
+pub(crate) enum Node<'a, V>{
+    Map(&'a [(V, V)]),
+    Array(&'a [V]),
+    Scalar
+}
+
+
+/// Map keys paths are matched against: the ones loading as python `str`s
+/// and `int`s. Other keys (bytes, floats, tuples...) never match, but stop
+/// integer and case insensitive lookups (`True == 1` in python).
+pub(crate) enum Key<'a>{
+    Str(&'a str),
+    Int(i128),
+    Other
+}
+
+
+/// A decoded value, seen the way it is converted to python.
+pub(crate) trait Tree: Sized {
+    fn node(&self) -> Node<'_, Self>;
+    fn key(&self) -> Key<'_>;
+}
+
+
+/// Value of the last entry keyed `matches` (python dicts keep the last value
+/// of duplicate keys).
+fn last<V: Tree>(map: &[(V, V)], matches: impl Fn(&Key) -> bool) -> Option<&V> {
+    map.iter().rev().find(|(key, _)| matches(&key.key())).map(|(_, item)| item)
+}
+
+
+fn map_item<'a, V: Tree>(map: &'a [(V, V)], segment: &Segment, ignorecase: bool) -> Option<&'a V> {
+    let plain = || map.iter().all(|(key, _)| !matches!(key.key(), Key::Other));
+    let mut arg = match segment {
+        Segment::Key(key) => key.to_owned(),
+        Segment::Int(int) if plain() => return last(map, |key| matches!(key, Key::Int(k) if *k == *int as i128)),
+        _ => return None
+    };
+    if ignorecase{
+        let lowered_arg = arg.to_lowercase();
+        arg = map.iter()
+            .map(|(key, _)| match key.key() {
+                Key::Str(key) => Some(key.to_owned()),
+                Key::Int(key) => Some(key.to_string()),
+                Key::Other => None
+            })
+            .find(|key| key.as_ref().is_none_or(|key| key.to_lowercase() == lowered_arg))??;
+    }
+    if let Ok(num_arg) = arg.parse::<i32>(){
+        if !plain(){
+            return None
+        }
+        let item = last(map, |key| matches!(key, Key::Int(k) if *k == num_arg as i128));
+        if item.is_some(){
+            return item
+        }
+    }
+    last(map, |key| matches!(key, Key::Str(k) if *k == arg))
+}
+
+
+fn array_item<'a, V>(list: &'a [V], segment: &Segment) -> Option<&'a V> {
+    let position = match segment {
+        Segment::Key(key) => list_position(key, list.len())?,
+        Segment::Int(int) => list_position(&int.to_string(), list.len())?,
+        _ => return None
+    };
+    Some(&list[position])
+}
+
+
+/// Walks `segments` over `value` as far as they certainly resolve, returning
+/// how many did and the value they lead to.
+pub(crate) fn resolve<'a, V: Tree>(value: &'a V, segments: &[Segment], ignorecase: bool) -> (usize, &'a V) {
+    let mut inner_object = value;
+    for (resolved, segment) in segments.iter().enumerate(){
+        let item = match inner_object.node() {
+            Node::Map(map) => map_item(map, segment, ignorecase),
+            Node::Array(list) => array_item(list, segment),
+            Node::Scalar => None
+        };
+        match item {
+            Some(item) => inner_object = item,
+            None => return (resolved, inner_object)
+        }
+    }
+    (segments.len(), inner_object)
+}
@@ -0,0 +1,242 @@
+//! XML documents (behind the `xml` feature).
+//!
+//! Elements are mapped into the dicts and lists `dictor` walks:
+//!
+//! - the document loads as `{root_tag: root_value}`;
+//! - attributes are stored under `@name` keys;
+//! - child elements are stored under their tag, repeated ones as a list
+//!   (as are the tags listed in `force_list`, even when they appear once);
+//! - an element holding only text loads as that text (`None` when empty),
+//!   otherwise its text is stored under `#text` according to `mixed`;
+//! - text is kept as written, but for the whitespace-only fragments
+//!   indenting elements, unless `strip` trims it.
+//!
+//! So `<feed><entry href="a"/><entry href="b"/></feed>` is addressed as
+//! `feed.entry.0.@href`. Namespaced names keep their prefix (`atom:link`).
+
+use std::collections::{HashMap, HashSet};
+
+use pyo3::exceptions::PyValueError;
+use pyo3::types::{PyDict, PyList, PyString};
+use pyo3::{pyfunction, PyAny, PyObject, PyResult, Python, ToPyObject};
+use roxmltree::{Document, Node, ParsingOptions};
+
+use crate::{dictor, read_text_or_path, PathArg, RType};
+
+
+const TEXT_KEY: &str = "#text";
+const ATTRIBUTE_PREFIX: &str = "@";
+
+
+/// What to do with the text of elements that also have attributes or
+/// children.
+#[derive(Clone, Copy)]
+enum MixedContent{
+    /// Concatenate the text fragments under `#text` (joined by a space when
+    /// trimmed)
+    Text,
+    /// Keep every text fragment, in order, as a list under `#text`
+    List,
+    /// Drop the text
+    Ignore
+}
+
+impl TryFrom<&str> for MixedContent{
+    type Error = pyo3::PyErr;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "text" => Ok(MixedContent::Text),
+            "list" => Ok(MixedContent::List),
+            "ignore" => Ok(MixedContent::Ignore),
+            _ => Err(PyValueError::new_err(format!("invalid mixed content strategy: {value} (expected text, list or ignore)")))
+        }
+    }
+}
+
+
+struct Loader{
+    force_list: HashSet<String>,
+    mixed: MixedContent,
+    strip: bool
+}
+
+impl Loader {
+    fn qualified_name(node: &Node, name: &str, namespace: Option<&str>) -> String {
+        match namespace.and_then(|uri| node.lookup_prefix(uri)) {
+            Some(prefix) => format!("{prefix}:{name}"),
+            None => name.to_owned()
+        }
+    }
+
+    fn element_to_py(&self, py: Python, node: Node) -> PyResult<PyObject> {
+        let texts: Vec<&str> = node.children()
+            .filter(|child| child.is_text())
+            .filter_map(|child| child.text())
+            .filter(|text| !text.trim().is_empty())
+            .map(|text| if self.strip { text.trim() } else { text })
+            .collect();
+        let text = || match self.strip {
+            true => texts.join(" "),
+            false => texts.concat()
+        };
+        let children: Vec<(String, Node)> = node.children()
+            .filter(|child| child.is_element())
+            .map(|child| (Loader::qualified_name(&child, child.tag_name().name(), child.tag_name().namespace()), child))
+            .collect();
+
+        if children.is_empty() && node.attributes().len() == 0{
+            return Ok(match texts.is_empty() {
+                true => py.None(),
+                false => text().to_object(py)
+            })
+        }
+
+        let dict = PyDict::new(py);
+        for attribute in node.attributes(){
+            let name = Loader::qualified_name(&node, attribute.name(), attribute.namespace());
+            dict.set_item(format!("{ATTRIBUTE_PREFIX}{name}"), attribute.value())?;
+        }
+        let mut occurrences: HashMap<&str, usize> = HashMap::new();
+        for (tag, _) in &children{
+            *occurrences.entry(tag).or_default() += 1;
+        }
+        for (tag, child) in &children{
+            let value = self.element_to_py(py, *child)?;
+            // repeated children, or forced ones, load as a list
+            if occurrences[tag.as_str()] > 1 || self.force_list.contains(tag){
+                match dict.get_item(tag) {
+                    Some(siblings) => siblings.downcast::<PyList>()?.append(value)?,
+                    None => dict.set_item(tag, PyList::new(py, [value]))?
+                }
+            }else{
+                dict.set_item(tag, value)?;
+            }
+        }
+        if !texts.is_empty(){
+            match self.mixed {
+                MixedContent::Text => dict.set_item(TEXT_KEY, text())?,
+                MixedContent::List => dict.set_item(TEXT_KEY, PyList::new(py, texts))?,
+                MixedContent::Ignore => {}
+            }
+        }
+        Ok(dict.to_object(py))
+    }
+}
+
+
+/// Loads an XML text (or file) into python objects.
+#[pyfunction]
+pub(crate) fn load_xml(py: Python, source: &PyAny, force_list: Option<Vec<String>>, mixed: Option<&str>, strip: Option<bool>) -> PyResult<PyObject> {
+    let text = read_text_or_path(source)?;
+    let options = ParsingOptions { allow_dtd: true, ..ParsingOptions::default() };
+    let document = Document::parse_with_options(&text, options).map_err(|e| PyValueError::new_err(format!("invalid XML: {e}")))?;
+    let loader = Loader {
+        force_list: force_list.unwrap_or_default().into_iter().collect(),
+        mixed: mixed.unwrap_or("text").try_into()?,
+        strip: strip.unwrap_or(false)
+    };
+    let root = document.root_element();
+    let dict = PyDict::new(py);
+    let tag = Loader::qualified_name(&root, root.tag_name().name(), root.tag_name().namespace());
+    dict.set_item(PyString::new(py, &tag), loader.element_to_py(py, root)?)?;
+    Ok(dict.to_object(py))
+}
+
+
+/// Same as `dictor` but takes an XML text or file.
+#[pyfunction]
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn dictor_xml(py: Python,
+    source: &PyAny,
+    path: Option<PathArg>,
+    default: Option<PyObject>,
+    checknone: Option<bool>,
+    ignorecase: Option<bool>,
+    pathsep: Option<String>,
+    search: Option<String>,
+    rtype: Option<RType>,
+    rounding: Option<&str>,
+    strict: Option<bool>,
+    unit: Option<&str>,
+    tz: Option<&str>,
+    on_error: Option<&str>,
+    force_list: Option<Vec<String>>,
+    mixed: Option<&str>,
+    strip: Option<bool>
+) -> PyResult<Option<PyObject>> {
+    let data = load_xml(py, source, force_list, mixed, strip)?;
+    dictor(py, data.as_ref(py), path, default, checknone, ignorecase, pathsep, search, rtype, rounding, strict, unit, tz, on_error, None)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FEED: &str = r#"<?xml version="1.0"?>
+<feed xmlns="http://www.w3.org/2005/Atom" xmlns:media="http://search.yahoo.com/mrss/">
+  <title> Example </title>
+  <entry id="1"><link href="http://a"/><title>first</title></entry>
+  <entry id="2"><link href="http://b"/><media:thumbnail url="t.png"/></entry>
+  <summary>some <b>bold</b> text</summary>
+  <empty/>
+</feed>"#;
+
+    fn lookup(py: Python, path: &str, force_list: Option<Vec<String>>, mixed: Option<&str>) -> Option<PyObject> {
+        lookup_stripped(py, path, force_list, mixed, None)
+    }
+
+    fn lookup_stripped(py: Python, path: &str, force_list: Option<Vec<String>>, mixed: Option<&str>, strip: Option<bool>) -> Option<PyObject> {
+        let source = PyString::new(py, FEED);
+        dictor_xml(py, source, Some(path.into()), None, None, None, None, None, None, None, None, None, None, None, force_list, mixed, strip).unwrap()
+    }
+
+    #[test]
+    fn elements_attributes_and_text(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let res = lookup(py, "feed.entry.1.link.@href", None, None).unwrap();
+            assert_eq!(res.to_string(), "http://b");
+            let res = lookup(py, "feed.entry.0.@id", None, None).unwrap();
+            assert_eq!(res.to_string(), "1");
+            let res = lookup(py, "feed.title", None, None).unwrap();
+            assert_eq!(res.to_string(), " Example ");
+            let res = lookup_stripped(py, "feed.title", None, None, Some(true)).unwrap();
+            assert_eq!(res.to_string(), "Example");
+            let res = lookup(py, "feed.entry.1.media:thumbnail.@url", None, None).unwrap();
+            assert_eq!(res.to_string(), "t.png");
+            assert!(lookup(py, "feed.empty", None, None).unwrap().is_none(py));
+        });
+    }
+
+    #[test]
+    fn force_list(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            assert!(lookup(py, "feed.entry.0.link.0.@href", None, None).is_none());
+            let res = lookup(py, "feed.entry.0.link.0.@href", Some(vec!["link".into()]), None).unwrap();
+            assert_eq!(res.to_string(), "http://a");
+        });
+    }
+
+    #[test]
+    fn mixed_content_strategies(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let res = lookup(py, "feed.summary.#text", None, None).unwrap();
+            assert_eq!(res.to_string(), "some  text");
+            let res = lookup_stripped(py, "feed.summary.#text", None, None, Some(true)).unwrap();
+            assert_eq!(res.to_string(), "some text");
+            let res = lookup(py, "feed.summary.#text", None, Some("list")).unwrap();
+            assert_eq!(res.extract::<Vec<String>>(py).unwrap(), vec!["some ", " text"]);
+            let res = lookup_stripped(py, "feed.summary.#text", None, Some("list"), Some(true)).unwrap();
+            assert_eq!(res.extract::<Vec<String>>(py).unwrap(), vec!["some", "text"]);
+            // indentation is not text
+            assert!(lookup(py, "feed.#text", None, None).is_none());
+            assert!(lookup(py, "feed.summary.#text", None, Some("ignore")).is_none());
+            let res = lookup(py, "feed.summary.b", None, Some("ignore")).unwrap();
+            assert_eq!(res.to_string(), "bold");
+        });
+    }
+}
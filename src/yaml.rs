@@ -0,0 +1,188 @@
+//! YAML documents (behind the `yaml` feature).
+//!
+//! Documents are loaded into python objects with anchors/aliases resolved and
+//! merge keys (`<<`) applied, then walked by `dictor` itself. Streams holding
+//! several documents are only loaded with `all_documents`, as a list whose
+//! leading path segment picks the document:
+//! `dictor_yaml(stream, "1.spec.replicas", all_documents=True)`.
+//!
+//! Sequence keys load as tuples (so they can be looked up with lists of
+//! keys), while mapping keys are rejected.
+
+use pyo3::exceptions::PyValueError;
+use pyo3::types::{PyDict, PyList, PyTuple};
+use pyo3::{pyfunction, PyAny, PyObject, PyResult, Python, ToPyObject};
+use serde::Deserialize;
+use serde_yaml::Value;
+
+use crate::{dictor, read_text_or_path, PathArg, RType};
+
+
+/// Converts `value`, building tuples instead of lists for keys so that
+/// sequence keys remain hashable.
+fn to_py(py: Python, value: &Value, as_key: bool) -> PyResult<PyObject> {
+    Ok(match value {
+        Value::Null => py.None(),
+        Value::Bool(b) => b.to_object(py),
+        Value::Number(n) => {
+            if let Some(i) = n.as_i64(){
+                i.to_object(py)
+            }else if let Some(u) = n.as_u64(){
+                u.to_object(py)
+            }else{
+                n.as_f64().unwrap_or(f64::NAN).to_object(py)
+            }
+        },
+        Value::String(s) => s.to_object(py),
+        Value::Sequence(list) => {
+            let items = list.iter().map(|item| to_py(py, item, as_key)).collect::<PyResult<Vec<_>>>()?;
+            if as_key{
+                PyTuple::new(py, items).to_object(py)
+            }else{
+                PyList::new(py, items).to_object(py)
+            }
+        },
+        Value::Mapping(_) if as_key => return Err(PyValueError::new_err("invalid YAML: mappings cannot be used as keys")),
+        Value::Mapping(map) => {
+            let dict = PyDict::new(py);
+            for (key, item) in map{
+                dict.set_item(to_py(py, key, true)?, to_py(py, item, false)?)?;
+            }
+            dict.to_object(py)
+        },
+        // custom tags carry no meaning for lookups
+        Value::Tagged(tagged) => to_py(py, &tagged.value, as_key)?
+    })
+}
+
+
+/// Loads a YAML text (or file) into python objects. With `all_documents`
+/// the stream loads as the list of its documents, otherwise it must hold a
+/// single one (or none, loading as `None`).
+#[pyfunction]
+pub(crate) fn load_yaml(py: Python, source: &PyAny, all_documents: Option<bool>) -> PyResult<PyObject> {
+    let text = read_text_or_path(source)?;
+    let mut documents = vec![];
+    for document in serde_yaml::Deserializer::from_str(&text){
+        let mut value = Value::deserialize(document).map_err(|e| PyValueError::new_err(format!("invalid YAML: {e}")))?;
+        value.apply_merge().map_err(|e| PyValueError::new_err(format!("invalid YAML: {e}")))?;
+        documents.push(value);
+    }
+    if all_documents.unwrap_or(false){
+        let documents = documents.iter().map(|document| to_py(py, document, false)).collect::<PyResult<Vec<_>>>()?;
+        return Ok(PyList::new(py, documents).to_object(py))
+    }
+    match documents.as_slice() {
+        [] => Ok(py.None()),
+        [document] => to_py(py, document, false),
+        _ => Err(PyValueError::new_err(format!("the YAML stream holds {} documents, load them with all_documents=True", documents.len())))
+    }
+}
+
+
+/// Same as `dictor` but takes a YAML text or file.
+#[pyfunction]
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn dictor_yaml(py: Python,
+    source: &PyAny,
+    path: Option<PathArg>,
+    default: Option<PyObject>,
+    checknone: Option<bool>,
+    ignorecase: Option<bool>,
+    pathsep: Option<String>,
+    search: Option<String>,
+    rtype: Option<RType>,
+    rounding: Option<&str>,
+    strict: Option<bool>,
+    unit: Option<&str>,
+    tz: Option<&str>,
+    on_error: Option<&str>,
+    all_documents: Option<bool>
+) -> PyResult<Option<PyObject>> {
+    let data = load_yaml(py, source, all_documents)?;
+    dictor(py, data.as_ref(py), path, default, checknone, ignorecase, pathsep, search, rtype, rounding, strict, unit, tz, on_error, None)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use pyo3::types::PyString;
+
+    use super::*;
+
+    const STREAM: &str = "\
+base: &base
+  image: nginx
+  replicas: 1
+service:
+  <<: *base
+  replicas: 3
+  ports: [80, 443]
+  42: answer
+---
+name: second
+";
+
+    #[test]
+    fn aliases_and_merge_keys_are_resolved(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let source = PyString::new(py, STREAM);
+            let res = dictor_yaml(py, source, Some("0.service.image".into()), None, None,
+                None, None, None, None, None, None, None, None, None, Some(true)).unwrap().unwrap();
+            assert_eq!(res.extract::<String>(py).unwrap(), "nginx");
+            let res = dictor_yaml(py, source, Some("0.service.replicas".into()), None, None,
+                None, None, None, None, None, None, None, None, None, Some(true)).unwrap().unwrap();
+            assert_eq!(res.extract::<usize>(py).unwrap(), 3);
+            let res = dictor_yaml(py, source, Some("0.service.42".into()), None, None,
+                None, None, None, None, None, None, None, None, None, Some(true)).unwrap().unwrap();
+            assert_eq!(res.extract::<String>(py).unwrap(), "answer");
+        });
+    }
+
+    #[test]
+    fn documents_addressed_by_index(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let source = PyString::new(py, STREAM);
+            let res = dictor_yaml(py, source, Some("1.name".into()), None, None,
+                None, None, None, None, None, None, None, None, None, Some(true)).unwrap().unwrap();
+            assert_eq!(res.extract::<String>(py).unwrap(), "second");
+
+            let e = load_yaml(py, source, None).unwrap_err();
+            assert!(e.to_string().contains("holds 2 documents"));
+
+            let single = PyString::new(py, "name: single\n");
+            let res = dictor_yaml(py, single, Some("name".into()), None, None,
+                None, None, None, None, None, None, None, None, None, None).unwrap().unwrap();
+            assert_eq!(res.extract::<String>(py).unwrap(), "single");
+            let res = dictor_yaml(py, single, Some("0.name".into()), None, None,
+                None, None, None, None, None, None, None, None, None, Some(true)).unwrap().unwrap();
+            assert_eq!(res.extract::<String>(py).unwrap(), "single");
+        });
+    }
+
+    #[test]
+    fn complex_keys(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let source = PyString::new(py, "? [a, [b, c]]\n: pair\n");
+            let keys = py.eval("[('a', ('b', 'c'))]", None, None).unwrap();
+            let res = dictor_yaml(py, source, Some(PathArg::Keys(keys)), None, None,
+                None, None, None, None, None, None, None, None, None, None).unwrap().unwrap();
+            assert_eq!(res.extract::<String>(py).unwrap(), "pair");
+            let source = PyString::new(py, "? {a: 1}\n: mapping\n");
+            let e = load_yaml(py, source, None).unwrap_err();
+            assert!(e.to_string().contains("mappings cannot be used as keys"));
+        });
+    }
+
+    #[test]
+    fn invalid_yaml(){
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let source = PyString::new(py, "key: [unclosed\n");
+            assert!(load_yaml(py, source, None).is_err());
+        });
+    }
+}
@@ -0,0 +1,94 @@
+//! Runs the `dictor` binary over the documents the python tests use.
+
+use assert_cmd::Command;
+
+
+fn dictor(args: &[&str]) -> Command {
+    let mut command = Command::cargo_bin("dictor").unwrap();
+    command.args(args);
+    command
+}
+
+
+#[test]
+fn path_lookups(){
+    dictor(&["dicto_r/basic.json", "--path", "terminator.1.terminator 2.genre.0", "--raw"])
+        .assert().success().stdout("nuclear war\n");
+    dictor(&["dicto_r/basic.json", "--path", r"dirty\.harry.GENRE", "--ignorecase", "--raw"])
+        .assert().success().stdout("romance\n");
+    dictor(&["dicto_r/basic.json", "--path", "spaceballs/year", "--pathsep", "/"])
+        .assert().success().stdout("1987\n");
+}
+
+#[test]
+fn missing_values(){
+    dictor(&["dicto_r/basic.json", "--path", "non.existent"])
+        .assert().success().stdout("null\n");
+    dictor(&["dicto_r/basic.json", "--path", "non.existent", "--default", "[]"])
+        .assert().success().stdout("[]\n");
+    dictor(&["dicto_r/basic.json", "--path", "non.existent", "--checknone"])
+        .assert().code(1).stderr("dictor: value not found for search path: non.existent\n");
+    dictor(&["dicto_r/list.json", "--search", "nothing", "--checknone"])
+        .assert().code(1);
+}
+
+#[test]
+fn search_from_stdin(){
+    let list = std::fs::read_to_string("dicto_r/list.json").unwrap();
+    dictor(&["--search", "name", "--raw"]).write_stdin(list)
+        .assert().success().stdout("spaceballs\ngone with the wind\ntitanic\ntitanic\n");
+}
+
+#[test]
+fn rtype_casts(){
+    let cast = |value: &str, rtype: &str| {
+        let output = dictor(&["--path", "value", "--rtype", rtype, "--raw"])
+            .write_stdin(format!(r#"{{"value": {value}}}"#))
+            .assert().success().get_output().stdout.clone();
+        String::from_utf8(output).unwrap().trim_end().to_owned()
+    };
+    assert_eq!(cast("1987", "str"), "1987");
+    assert_eq!(cast(r#"" 1983 ""#, "int"), "1983");
+    assert_eq!(cast(r#""2.5""#, "int"), "2");
+    assert_eq!(cast("-2.5", "int"), "-2");
+    assert_eq!(cast(r#""x""#, "int"), "x");
+    assert_eq!(cast(r#""1e3""#, "float"), "1000.0");
+    assert_eq!(cast(r#""Yes""#, "bool"), "true");
+    assert_eq!(cast("0", "bool"), "false");
+    assert_eq!(cast("null", "list"), "[]");
+    assert_eq!(cast("3", "list"), "[3]");
+    assert_eq!(cast(r#""{\"a\": 1}""#, "dict"), r#"{"a":1}"#);
+    assert_eq!(cast(r#"[["a", 1]]"#, "dict"), r#"{"a":1}"#);
+    // same conversions as the library
+    assert_eq!(cast(r#""1_000""#, "int"), "1000");
+    assert_eq!(cast(r#""123456789012345678901234567890.9""#, "int"), "123456789012345678901234567890");
+    assert_eq!(cast(r#""9007199254740993.5""#, "int"), "9007199254740993");
+    assert_eq!(cast("2.5", "str"), "2.5");
+    assert_eq!(cast("true", "str"), "True");
+    assert_eq!(cast(r#"{"a": [null]}"#, "str"), "{'a': [None]}");
+}
+
+#[test]
+fn rtype_options(){
+    let convert = |value: &str, options: &[&str]| {
+        let mut args = vec!["--path", "value", "--raw"];
+        args.extend(options);
+        dictor(&args).write_stdin(format!(r#"{{"value": {value}}}"#)).assert()
+    };
+    convert(r#""2.5""#, &["--rtype", "int", "--rounding", "half_up"]).success().stdout("3\n");
+    convert("-2.5", &["--rtype", "int", "--rounding", "floor"]).success().stdout("-3\n");
+    convert(r#""x""#, &["--rtype", "int", "--default", "0"]).success().stdout("0\n");
+    convert(r#""x""#, &["--rtype", "int", "--strict"]).code(3).stderr("dictor: cannot convert \"x\" to int\n");
+    convert("1", &["--rounding", "floor"]).failure();
+}
+
+#[test]
+fn bad_input(){
+    dictor(&["dicto_r/missing.json", "--path", "a"]).assert().code(2);
+    let output = dictor(&["--path", "a"]).write_stdin("{not json").assert().code(2).get_output().stderr.clone();
+    assert!(String::from_utf8(output).unwrap().starts_with("dictor: invalid JSON"));
+    dictor(&["dicto_r/basic.json", "--path", "a..b"]).assert().code(2);
+    dictor(&["dicto_r/basic.json", "--path", "a[1:]"]).assert().code(2);
+    // --path or --search is required
+    dictor(&["dicto_r/basic.json"]).assert().failure();
+}